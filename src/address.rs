@@ -1,13 +1,85 @@
+use crate::{crc, Family, OneWireError, OneWireResult};
+use core::convert::Infallible;
 use core::fmt::{Error, Formatter};
+use core::str::FromStr;
 
 /// A 64-bit address of a device. These are globally unique, and used to single out a single device on
 /// a potentially crowded bus
-#[derive(Copy, Clone, PartialEq, Eq)]
+///
+/// Addresses are ordered the same way a device search finds them: by the bits in the order they
+/// are sent on the bus, starting from the least significant bit of the family code. This is not
+/// the same as the numeric order of the `u64` values
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Address(pub u64);
 
 impl Address {
-    pub fn family_code(&self) -> u8 {
-        self.0.to_le_bytes()[0]
+    /// Creates an address from its ROM bytes, in the order they are sent on the bus (family code
+    /// first). Returns `CrcMismatch` if the crc in the last byte is wrong
+    pub fn from_rom_bytes(bytes: [u8; 8]) -> OneWireResult<Address, Infallible> {
+        crc::check_crc8(&bytes)?;
+        Ok(Address(u64::from_le_bytes(bytes)))
+    }
+
+    /// Creates an address without checking its crc, such as for a known address in a const
+    pub const fn new_unchecked(value: u64) -> Address {
+        Address(value)
+    }
+
+    /// The family code (the first byte sent on the bus), which identifies the type of device
+    pub const fn family_code(&self) -> u8 {
+        self.0 as u8
+    }
+
+    /// Checks the crc in the last byte against the first 7 bytes, such as for an address from
+    /// configuration or user input. Addresses found by a search are already checked
+    pub fn is_valid_crc(&self) -> bool {
+        crc::crc8(&self.to_le_bytes()) == 0
+    }
+
+    /// The type of the device, from its family code
+    pub fn family(&self) -> Family {
+        Family::from(self.family_code())
+    }
+
+    /// Checks that the address has the family code `family`, such as before using a device driver.
+    /// Returns `WrongFamily` if not
+    pub fn expect_family<E>(&self, family: u8) -> OneWireResult<(), E> {
+        if self.family_code() != family {
+            return Err(OneWireError::WrongFamily {
+                expected: family,
+                found: self.family_code(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The 48-bit serial number, which is unique within a family
+    pub const fn serial_number(&self) -> u64 {
+        (self.0 >> 8) & 0xFFFF_FFFF_FFFF
+    }
+
+    /// The bytes of the address, in the order they are sent on the bus (family code first).
+    /// The address is stored as a `u64`, so the bytes can't be borrowed, but converting them is
+    /// free: the array is only 8 bytes, and compiles down to shifts of the value in a register
+    pub fn to_le_bytes(&self) -> [u8; 8] {
+        self.0.to_le_bytes()
+    }
+
+    /// Returns the position of this address in the order a device search would find the addresses
+    /// in `all`, or `None` if this address isn't in `all`.
+    /// A search chooses the lower bit value first, starting with the least significant bit, so this
+    /// is not the same as the numeric order of the addresses
+    pub fn enumeration_index(&self, all: &[Address]) -> Option<usize> {
+        if !all.contains(self) {
+            return None;
+        }
+        Some(all.iter().filter(|address| *address < self).count())
+    }
+
+    /// A value that sorts in the same order that addresses are found by a search
+    pub(crate) fn search_key(&self) -> u64 {
+        self.0.reverse_bits()
     }
 }
 
@@ -16,3 +88,220 @@ impl core::fmt::Debug for Address {
         write!(f, "{:016X?}", self.0)
     }
 }
+
+impl Ord for Address {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.search_key().cmp(&other.search_key())
+    }
+}
+
+impl PartialOrd for Address {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Formats the address as its ROM bytes in the order they are sent on the bus, family code first,
+/// as in Maxim datasheets (e.g. `28:10:CD:1F:0B:00:00:E8`)
+impl core::fmt::Display for Address {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        for (index, byte) in self.to_le_bytes().iter().enumerate() {
+            if index > 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{:02X}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Same as `Display`, with lowercase hex (e.g. `28:10:cd:1f:0b:00:00:e8`)
+impl core::fmt::LowerHex for Address {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        for (index, byte) in self.to_le_bytes().iter().enumerate() {
+            if index > 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses an address from its ROM bytes in hex, family code first (the same order as `Display`),
+/// either with or without colons between the bytes (e.g. `28:10:CD:1F:0B:00:00:E8` or
+/// `2810CD1F0B0000E8`).
+/// Returns `InvalidAddress` if the string isn't in either format, or `CrcMismatch` if the crc of
+/// the address is wrong
+impl FromStr for Address {
+    type Err = OneWireError<Infallible>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (stride, separated) = match s.len() {
+            16 => (2, false),
+            23 => (3, true),
+            _ => return Err(OneWireError::InvalidAddress),
+        };
+        let mut bytes = [0; 8];
+        for (index, byte) in bytes.iter_mut().enumerate() {
+            let start = index * stride;
+            if separated && index > 0 && s.get(start - 1..start) != Some(":") {
+                return Err(OneWireError::InvalidAddress);
+            }
+            let digits = s
+                .get(start..start + 2)
+                .ok_or(OneWireError::InvalidAddress)?;
+            if !digits.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+                return Err(OneWireError::InvalidAddress);
+            }
+            *byte = u8::from_str_radix(digits, 16).map_err(|_| OneWireError::InvalidAddress)?;
+        }
+        Address::from_rom_bytes(bytes)
+    }
+}
+
+/// Formats the address as its ROM bytes, family code first, as in Maxim datasheets
+/// (e.g. `28:10:CD:1F:0B:00:00:E8`)
+#[cfg(feature = "defmt")]
+impl defmt::Format for Address {
+    fn format(&self, f: defmt::Formatter) {
+        let bytes = self.to_le_bytes();
+        defmt::write!(
+            f,
+            "{=u8:02X}:{=u8:02X}:{=u8:02X}:{=u8:02X}:{=u8:02X}:{=u8:02X}:{=u8:02X}:{=u8:02X}",
+            bytes[0],
+            bytes[1],
+            bytes[2],
+            bytes[3],
+            bytes[4],
+            bytes[5],
+            bytes[6],
+            bytes[7]
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use crate::{Address, OneWireError};
+    use std::format;
+
+    #[test]
+    fn test_enumeration_index() {
+        let all = [
+            Address(0x0B00000B20687E28),
+            Address(0xE800000B1FCD1028),
+            Address(0x70000008AC851628),
+        ];
+        assert_eq!(all[1].enumeration_index(&all), Some(0));
+        assert_eq!(all[2].enumeration_index(&all), Some(1));
+        assert_eq!(all[0].enumeration_index(&all), Some(2));
+        assert_eq!(Address(0x28).enumeration_index(&all), None);
+    }
+
+    #[test]
+    fn test_ord() {
+        let mut all = [
+            Address(0x0B00000B20687E28),
+            Address(0xE800000B1FCD1028),
+            Address(0x70000008AC851628),
+        ];
+        all.sort();
+        assert_eq!(
+            all,
+            [
+                Address(0xE800000B1FCD1028),
+                Address(0x70000008AC851628),
+                Address(0x0B00000B20687E28),
+            ]
+        );
+        assert!(Address(0xE800000B1FCD1028) < Address(0x0B00000B20687E28));
+    }
+
+    #[test]
+    fn test_to_le_bytes() {
+        let address = Address(0xE800000B1FCD1028);
+        assert_eq!(
+            address.to_le_bytes(),
+            [0x28, 0x10, 0xCD, 0x1F, 0x0B, 0x00, 0x00, 0xE8]
+        );
+        assert_eq!(address.family_code(), 0x28);
+    }
+
+    #[test]
+    fn test_expect_family() {
+        let address = Address(0xE800000B1FCD1028);
+        assert!(address.expect_family::<()>(0x28).is_ok());
+        assert!(matches!(
+            address.expect_family::<()>(0x10),
+            Err(OneWireError::WrongFamily {
+                expected: 0x10,
+                found: 0x28
+            })
+        ));
+    }
+
+    #[test]
+    fn test_display() {
+        let address = Address(0xC3012A0C1E64FF28);
+        assert_eq!(format!("{}", address), "28:FF:64:1E:0C:2A:01:C3");
+        assert_eq!(format!("{:x}", address), "28:ff:64:1e:0c:2a:01:c3");
+        assert_eq!(format!("{:?}", address), "C3012A0C1E64FF28");
+    }
+
+    #[test]
+    fn test_from_rom_bytes() {
+        const KNOWN: Address = Address::new_unchecked(0xE800000B1FCD1028);
+        assert_eq!(
+            Address::from_rom_bytes([0x28, 0x10, 0xCD, 0x1F, 0x0B, 0x00, 0x00, 0xE8]).unwrap(),
+            KNOWN
+        );
+        assert!(matches!(
+            Address::from_rom_bytes([0x28, 0x10, 0xCD, 0x1F, 0x0B, 0x00, 0x00, 0xE9]),
+            Err(OneWireError::CrcMismatch {
+                computed: 0xE8,
+                expected: 0xE9
+            })
+        ));
+    }
+
+    #[test]
+    fn test_from_str() {
+        let address = Address(0xE800000B1FCD1028);
+        assert_eq!(
+            "28:10:CD:1F:0B:00:00:E8".parse::<Address>().unwrap(),
+            address
+        );
+        assert_eq!("2810cd1f0b0000e8".parse::<Address>().unwrap(), address);
+        assert!(matches!(
+            "2810CD1F0B0000E9".parse::<Address>(),
+            Err(OneWireError::CrcMismatch { .. })
+        ));
+        for invalid in &[
+            "2810CD1F0B0000",
+            "28-10-CD-1F-0B-00-00-E8",
+            "2810CD1F0B0000+8",
+        ] {
+            assert!(matches!(
+                invalid.parse::<Address>(),
+                Err(OneWireError::InvalidAddress)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_is_valid_crc() {
+        assert!(Address(0xE800000B1FCD1028).is_valid_crc());
+        // one bit of the serial number flipped
+        assert!(!Address(0xE800000B1FCD1128).is_valid_crc());
+    }
+
+    #[test]
+    fn test_serial_number() {
+        let address = Address(0xE800000B1FCD1028);
+        assert_eq!(address.family_code(), 0x28);
+        assert_eq!(address.serial_number(), 0x00000B1FCD10);
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{OneWire, OneWireResult, Phase};
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+impl<T, E> OneWire<T>
+where
+    T: InputPin<Error = E>,
+    T: OutputPin<Error = E>,
+{
+    /// Same as `write_bytes`, but each bit is written inside a critical section, so an interrupt
+    /// can't stretch a time slot. Interrupts can still run between bits, so they are never
+    /// blocked for longer than a single slot
+    pub fn write_bytes_atomic(
+        &mut self,
+        bytes: &[u8],
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E> {
+        for byte in bytes {
+            self.reselect_for_byte(delay)?;
+            self.phase = Phase::DataWrite;
+            for bit in 0..8 {
+                let value = byte & (1 << bit) != 0;
+                critical_section::with(|_| self.write_bit(value, delay))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Same as `read_bytes`, but each bit is read inside a critical section, so an interrupt
+    /// can't stretch a time slot. Interrupts can still run between bits, so they are never
+    /// blocked for longer than a single slot
+    pub fn read_bytes_atomic(
+        &mut self,
+        output: &mut [u8],
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E> {
+        for byte in output.iter_mut() {
+            self.reselect_for_byte(delay)?;
+            self.phase = Phase::DataRead;
+            *byte = 0;
+            for bit in 0..8 {
+                if critical_section::with(|_| self.read_bit(delay))? {
+                    *byte |= 1 << bit;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use crate::mock::{Event, ReplayBus};
+    use crate::OneWire;
+    use std::vec;
+
+    #[test]
+    fn test_atomic_bytes() {
+        let bus = ReplayBus::new(vec![
+            Event::Reset { present: true },
+            Event::Write(0xCC),
+            Event::Write(0xBE),
+            Event::Read(0x50),
+            Event::Read(0x05),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        one_wire.reset(&mut delay).unwrap();
+        one_wire
+            .write_bytes_atomic(&[0xCC, 0xBE], &mut delay)
+            .unwrap();
+        let mut output = [0; 2];
+        one_wire.read_bytes_atomic(&mut output, &mut delay).unwrap();
+        assert_eq!(output, [0x50, 0x05]);
+        bus.finish();
+    }
+}
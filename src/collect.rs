@@ -0,0 +1,54 @@
+use crate::{Address, OneWire, OneWireError, OneWireResult};
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use heapless::Vec;
+
+impl<T, E> OneWire<T>
+where
+    T: InputPin<Error = E>,
+    T: OutputPin<Error = E>,
+{
+    /// Finds all devices on the bus (see `devices`), and collects their addresses.
+    /// Returns `BufferTooSmall` if there are more than `N` devices
+    pub fn collect_devices<const N: usize>(
+        &mut self,
+        only_alarming: bool,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<Vec<Address, N>, E> {
+        let mut devices = Vec::new();
+        for address in self.devices(only_alarming, delay) {
+            devices
+                .push(address?)
+                .map_err(|_| OneWireError::BufferTooSmall)?;
+        }
+        Ok(devices)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use crate::mock::{MockBus, MockDevice};
+    use crate::{Address, OneWire, OneWireError};
+    use std::vec;
+
+    #[test]
+    fn test_collect_devices() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let devices = one_wire.collect_devices::<2>(false, &mut delay).unwrap();
+        assert_eq!(
+            devices[..],
+            [Address(0xE800000B1FCD1028), Address(0x70000008AC851628)]
+        );
+        assert!(matches!(
+            one_wire.collect_devices::<1>(false, &mut delay),
+            Err(OneWireError::BufferTooSmall)
+        ));
+    }
+}
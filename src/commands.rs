@@ -1,5 +1,14 @@
 pub const SEARCH_NORMAL: u8 = 0xF0;
 pub const MATCH_ROM: u8 = 0x55;
+pub const OVERDRIVE_MATCH_ROM: u8 = 0x69;
+pub const READ_ROM: u8 = 0x33;
 pub const SKIP_ROM: u8 = 0xCC;
+pub const OVERDRIVE_SKIP_ROM: u8 = 0x3C;
 pub const SEARCH_ALARM: u8 = 0xEC;
+pub const RESUME: u8 = 0xA5;
 pub const READ_POWER_SUPPLY: u8 = 0xB4;
+pub const CONVERT_T: u8 = 0x44;
+pub const READ_SCRATCHPAD: u8 = 0xBE;
+pub const WRITE_SCRATCHPAD: u8 = 0x4E;
+pub const COPY_SCRATCHPAD: u8 = 0x48;
+pub const RECALL_E2: u8 = 0xB8;
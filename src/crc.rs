@@ -1,20 +1,41 @@
 use crate::error::{OneWireError, OneWireResult};
 
-/// Calculates the crc8 of the input data.
+/// Calculates the crc8 of the input data, using the Maxim/Dallas polynomial (x^8 + x^5 + x^4 + 1)
+/// that 1-Wire devices use for ROM codes and scratchpads.
 pub fn crc8(data: &[u8]) -> u8 {
-    let mut crc = 0;
+    let mut crc = Crc8::new();
     for byte in data {
-        let mut byte = *byte;
+        crc.update(*byte);
+    }
+    crc.value()
+}
+
+/// Calculates a crc8 one byte at a time, for data that isn't available all at once
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Crc8(u8);
+
+impl Crc8 {
+    pub fn new() -> Crc8 {
+        Crc8(0)
+    }
+
+    /// Adds the next byte of data to the crc
+    pub fn update(&mut self, mut byte: u8) {
         for _ in 0..8 {
-            let x = (byte ^ crc) & 0x01;
-            crc >>= 1;
+            let x = (byte ^ self.0) & 0x01;
+            self.0 >>= 1;
             if x != 0 {
-                crc ^= 0x8C;
+                self.0 ^= 0x8C;
             }
             byte >>= 1;
         }
     }
-    crc
+
+    /// The crc8 of all the data so far. As with `check_crc8`, this is 0 if the last byte was the
+    /// crc value of the data before it
+    pub fn value(&self) -> u8 {
+        self.0
+    }
 }
 
 /// Checks to see if data (including the crc byte) passes the crc check.
@@ -25,13 +46,62 @@ pub fn check_crc8<E>(data: &[u8]) -> OneWireResult<(), E> {
     if crc8(data) == 0 {
         Ok(())
     } else {
-        Err(OneWireError::CrcMismatch)
+        let (expected, data) = data.split_last().expect("the crc of no data is 0");
+        Err(OneWireError::CrcMismatch {
+            computed: crc8(data),
+            expected: *expected,
+        })
+    }
+}
+
+/// Checks to see if data (not including the crc byte) matches the separately received crc value.
+pub fn verify_crc8<E>(data: &[u8], expected: u8) -> OneWireResult<(), E> {
+    let computed = crc8(data);
+    if computed == expected {
+        Ok(())
+    } else {
+        Err(OneWireError::CrcMismatch { computed, expected })
+    }
+}
+
+/// Calculates the crc16 (polynomial 0xA001, as used by devices like the DS2408 and DS2423) of the
+/// input data, starting from `seed`. Use a seed of 0 for a new frame, or the previous result to
+/// continue a crc over data that arrives in several parts
+pub fn crc16(data: &[u8], seed: u16) -> u16 {
+    let mut crc = seed;
+    for byte in data {
+        crc ^= u16::from(*byte);
+        for _ in 0..8 {
+            if crc & 0x0001 != 0 {
+                crc = (crc >> 1) ^ 0xA001;
+            } else {
+                crc >>= 1;
+            }
+        }
+    }
+    crc
+}
+
+/// Checks to see if data passes the crc16 check, where the last two bytes are the inverted crc16
+/// of the data before them (least significant byte first), as devices send it
+pub fn check_crc16<E>(data: &[u8], seed: u16) -> OneWireResult<(), E> {
+    if data.len() < 2 {
+        return Err(OneWireError::BufferTooSmall);
+    }
+    let (data, crc) = data.split_at(data.len() - 2);
+    let computed = crc16(data, seed);
+    let expected = !u16::from_le_bytes([crc[0], crc[1]]);
+    if computed == expected {
+        Ok(())
+    } else {
+        Err(OneWireError::Crc16Mismatch { computed, expected })
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::crc::crc8;
+    use crate::crc::{check_crc16, crc16, crc8, verify_crc8, Crc8};
+    use crate::OneWireError;
 
     #[test]
     fn test_crc8() {
@@ -44,4 +114,58 @@ mod test {
         assert_eq!(crc8(&[95, 1, 75, 70, 127, 255, 1, 16]), 155);
         assert_eq!(crc8(&[95, 1, 75, 70, 127, 255, 1, 16, 155]), 0);
     }
+
+    #[test]
+    fn test_crc8_maxim_example() {
+        // the ROM code example from Maxim application note 27
+        assert_eq!(crc8(&[0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00]), 0xA2);
+        assert_eq!(crc8(&[0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00, 0xA2]), 0);
+    }
+
+    #[test]
+    fn test_crc8_incremental() {
+        let mut crc = Crc8::new();
+        for byte in &[99, 1, 75, 70, 127, 255, 13, 16] {
+            crc.update(*byte);
+        }
+        assert_eq!(crc.value(), 21);
+        crc.update(21);
+        assert_eq!(crc.value(), 0);
+    }
+
+    #[test]
+    fn test_verify_crc8() {
+        assert!(verify_crc8::<()>(&[99, 1, 75, 70, 127, 255, 13, 16], 21).is_ok());
+        assert!(matches!(
+            verify_crc8::<()>(&[99, 1, 75, 70, 127, 255, 13, 16], 20),
+            Err(OneWireError::CrcMismatch {
+                computed: 21,
+                expected: 20
+            })
+        ));
+    }
+
+    #[test]
+    fn test_crc16() {
+        // the standard check value for this crc16 (CRC-16/ARC)
+        assert_eq!(crc16(b"123456789", 0), 0xBB3D);
+        assert_eq!(crc16(b"56789", crc16(b"1234", 0)), 0xBB3D);
+
+        // a DS2408 Read PIO Registers frame: the command, target address, the registers from 0x88
+        // to 0x8F, then the inverted crc16
+        let frame = [
+            0xF0, 0x88, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x3B, 0x7A,
+        ];
+        assert!(check_crc16::<()>(&frame, 0).is_ok());
+        let mut corrupted = frame;
+        corrupted[3] = 0xFE;
+        assert!(matches!(
+            check_crc16::<()>(&corrupted, 0),
+            Err(OneWireError::Crc16Mismatch { .. })
+        ));
+        assert!(matches!(
+            check_crc16::<()>(&[0x00], 0),
+            Err(OneWireError::BufferTooSmall)
+        ));
+    }
 }
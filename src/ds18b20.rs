@@ -0,0 +1,415 @@
+//! A driver for the DS18B20 temperature sensor
+
+use crate::{commands, Address, OneWire, OneWireError, OneWireResult};
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// The family code of a DS18B20
+pub const FAMILY_CODE: u8 = 0x28;
+
+/// The resolution of temperature conversions, set in the configuration register
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// 0.5°C
+    Bits9,
+    /// 0.25°C
+    Bits10,
+    /// 0.125°C
+    Bits11,
+    /// 0.0625°C, the power on default
+    Bits12,
+}
+
+impl Resolution {
+    /// The value of the configuration register (byte 4 of the scratchpad) for this resolution
+    pub fn config_byte(&self) -> u8 {
+        let bits = match self {
+            Resolution::Bits9 => 0,
+            Resolution::Bits10 => 1,
+            Resolution::Bits11 => 2,
+            Resolution::Bits12 => 3,
+        };
+        (bits << 5) | 0x1F
+    }
+
+    /// The resolution set in a configuration register
+    pub fn from_config_byte(config: u8) -> Resolution {
+        match (config >> 5) & 0x03 {
+            0 => Resolution::Bits9,
+            1 => Resolution::Bits10,
+            2 => Resolution::Bits11,
+            _ => Resolution::Bits12,
+        }
+    }
+}
+
+/// The longest time (in microseconds) a conversion takes at the given resolution
+pub fn conversion_time(resolution: Resolution) -> u32 {
+    match resolution {
+        Resolution::Bits9 => 93_750,
+        Resolution::Bits10 => 187_500,
+        Resolution::Bits11 => 375_000,
+        Resolution::Bits12 => 750_000,
+    }
+}
+
+/// A DS18B20 temperature sensor at a known address
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Ds18b20 {
+    address: Address,
+}
+
+impl Ds18b20 {
+    /// Returns `WrongFamily` if the address isn't a DS18B20
+    pub fn new<E>(address: Address) -> OneWireResult<Ds18b20, E> {
+        address.expect_family(FAMILY_CODE)?;
+        Ok(Ds18b20 { address })
+    }
+
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Starts a temperature conversion with CONVERT_T. The conversion takes up to 750ms at 12-bit
+    /// resolution, after which the temperature can be read with `read_temperature`.
+    /// A parasite powered sensor needs a strong pull-up while converting
+    /// (see `OneWire::write_byte_with_power`)
+    pub fn start_conversion<T, E>(
+        &self,
+        onewire: &mut OneWire<T>,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E>
+    where
+        T: InputPin<Error = E>,
+        T: OutputPin<Error = E>,
+    {
+        onewire.send_command(commands::CONVERT_T, Some(&self.address), delay)?;
+        onewire.end_transaction(delay)
+    }
+
+    /// Reads the 9 byte scratchpad, and checks its crc (see `OneWire::read_scratchpad`)
+    pub fn read_scratchpad<T, E>(
+        &self,
+        onewire: &mut OneWire<T>,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<[u8; 9], E>
+    where
+        T: InputPin<Error = E>,
+        T: OutputPin<Error = E>,
+    {
+        onewire.read_scratchpad(&self.address, delay)
+    }
+
+    /// Reads the temperature from the last conversion, in thousandths of a degree Celsius
+    pub fn read_temperature<T, E>(
+        &self,
+        onewire: &mut OneWire<T>,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<i32, E>
+    where
+        T: InputPin<Error = E>,
+        T: OutputPin<Error = E>,
+    {
+        let scratchpad = self.read_scratchpad(onewire, delay)?;
+        Ok(temperature_milli_celsius(&scratchpad))
+    }
+
+    /// Sets the resolution of conversions with WRITE_SCRATCHPAD, keeping the alarm thresholds
+    /// already in the scratchpad. If `copy_to_eeprom` is true, the scratchpad is then copied to
+    /// EEPROM with COPY_SCRATCHPAD, so the resolution is kept after a power cycle.
+    /// A parasite powered sensor needs a strong pull-up while copying, so use
+    /// `OneWire::copy_scratchpad_with_power` for it instead
+    pub fn set_resolution<T, E>(
+        &self,
+        onewire: &mut OneWire<T>,
+        resolution: Resolution,
+        copy_to_eeprom: bool,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E>
+    where
+        T: InputPin<Error = E>,
+        T: OutputPin<Error = E>,
+    {
+        let scratchpad = self.read_scratchpad(onewire, delay)?;
+        self.write_scratchpad(
+            onewire,
+            [scratchpad[2], scratchpad[3], resolution.config_byte()],
+            copy_to_eeprom,
+            delay,
+        )
+    }
+
+    /// Sets the alarm thresholds (in whole degrees Celsius) with WRITE_SCRATCHPAD, keeping the
+    /// resolution already in the scratchpad. A sensor responds to an alarm search
+    /// (`only_alarming`) when its last conversion was above `high` or below `low`.
+    /// `copy_to_eeprom` is the same as for `set_resolution`
+    pub fn set_alarm_thresholds<T, E>(
+        &self,
+        onewire: &mut OneWire<T>,
+        high: i8,
+        low: i8,
+        copy_to_eeprom: bool,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E>
+    where
+        T: InputPin<Error = E>,
+        T: OutputPin<Error = E>,
+    {
+        let scratchpad = self.read_scratchpad(onewire, delay)?;
+        self.write_scratchpad(
+            onewire,
+            [high as u8, low as u8, scratchpad[4]],
+            copy_to_eeprom,
+            delay,
+        )
+    }
+
+    /// Reads the alarm thresholds (high, low) from the scratchpad, in whole degrees Celsius
+    pub fn alarm_thresholds<T, E>(
+        &self,
+        onewire: &mut OneWire<T>,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(i8, i8), E>
+    where
+        T: InputPin<Error = E>,
+        T: OutputPin<Error = E>,
+    {
+        let scratchpad = self.read_scratchpad(onewire, delay)?;
+        Ok((scratchpad[2] as i8, scratchpad[3] as i8))
+    }
+
+    /// Writes the alarm thresholds and configuration register (bytes 2 to 4 of the scratchpad),
+    /// and optionally copies them to EEPROM
+    fn write_scratchpad<T, E>(
+        &self,
+        onewire: &mut OneWire<T>,
+        bytes: [u8; 3],
+        copy_to_eeprom: bool,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E>
+    where
+        T: InputPin<Error = E>,
+        T: OutputPin<Error = E>,
+    {
+        onewire.send_command(commands::WRITE_SCRATCHPAD, Some(&self.address), delay)?;
+        onewire.write_bytes(&bytes, delay)?;
+        onewire.end_transaction(delay)?;
+        if copy_to_eeprom {
+            onewire.copy_scratchpad(&self.address, delay)?;
+        }
+        Ok(())
+    }
+}
+
+/// Starts a temperature conversion on every DS18B20 on the bus at once, with SKIP_ROM and
+/// CONVERT_T. Use `wait_conversion_done` to wait for the conversions to finish
+pub fn convert_all<T, E>(
+    onewire: &mut OneWire<T>,
+    delay: &mut impl DelayUs<u16>,
+) -> OneWireResult<(), E>
+where
+    T: InputPin<Error = E>,
+    T: OutputPin<Error = E>,
+{
+    onewire.send_command(commands::CONVERT_T, None, delay)?;
+    onewire.end_transaction(delay)
+}
+
+/// Waits for a conversion to finish by sending read slots, which converting sensors answer with
+/// a 0 and finished sensors answer with a 1. This is usually much shorter than waiting for the
+/// longest `conversion_time`, which is used as the timeout (returning `Timeout`).
+/// This must immediately follow `convert_all` or `Ds18b20::start_conversion`, and only works
+/// with externally powered sensors: a parasite powered sensor can't pull the bus low while
+/// converting, and needs a strong pull-up for the whole conversion time instead
+pub fn wait_conversion_done<T, E>(
+    onewire: &mut OneWire<T>,
+    delay: &mut impl DelayUs<u16>,
+) -> OneWireResult<(), E>
+where
+    T: InputPin<Error = E>,
+    T: OutputPin<Error = E>,
+{
+    let timing = *onewire.timing();
+    let slot_us = u32::from(timing.read_low_us)
+        + u32::from(timing.read_sample_us)
+        + u32::from(timing.read_recovery_us);
+    let mut elapsed = 0;
+    while !onewire.read_bit(delay)? {
+        if elapsed >= conversion_time(Resolution::Bits12) {
+            return Err(OneWireError::Timeout);
+        }
+        elapsed += slot_us;
+    }
+    onewire.end_transaction(delay)
+}
+
+/// Decodes the temperature in a scratchpad, in thousandths of a degree Celsius (rounded towards
+/// zero). The bits below the resolution set in the configuration register are undefined, so they
+/// are ignored
+pub fn temperature_milli_celsius(scratchpad: &[u8; 9]) -> i32 {
+    let undefined_bits = match Resolution::from_config_byte(scratchpad[4]) {
+        Resolution::Bits9 => 0b111,
+        Resolution::Bits10 => 0b11,
+        Resolution::Bits11 => 0b1,
+        Resolution::Bits12 => 0,
+    };
+    let raw = i16::from_le_bytes([scratchpad[0], scratchpad[1]]) & !undefined_bits;
+    // the raw value is in 1/16ths of a degree
+    i32::from(raw) * 125 / 2
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use crate::ds18b20::{
+        conversion_time, convert_all, temperature_milli_celsius, wait_conversion_done, Ds18b20,
+        Resolution,
+    };
+    use crate::mock::{Event, ReplayBus};
+    use crate::{Address, OneWire, OneWireError};
+    use std::vec;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_temperature_milli_celsius() {
+        // 12-bit, +25.0625°C
+        assert_eq!(
+            temperature_milli_celsius(&[0x91, 0x01, 0x4B, 0x46, 0x7F, 0xFF, 0x0F, 0x10, 0x25]),
+            25_062
+        );
+        // 12-bit, the +85°C power on value
+        assert_eq!(
+            temperature_milli_celsius(&[0x50, 0x05, 0x4B, 0x46, 0x7F, 0xFF, 0x0C, 0x10, 0x1C]),
+            85_000
+        );
+        // 9-bit, -10.125°C with the undefined bits set, which reads as -10.5°C
+        assert_eq!(
+            temperature_milli_celsius(&[0x5E, 0xFF, 0x4B, 0x46, 0x1F, 0xFF, 0x02, 0x10, 0x26]),
+            -10_500
+        );
+    }
+
+    #[test]
+    fn test_new() {
+        assert!(Ds18b20::new::<()>(Address(0xE800000B1FCD1028)).is_ok());
+        assert!(matches!(
+            Ds18b20::new::<()>(Address(0x0B00000B20687E10)),
+            Err(OneWireError::WrongFamily {
+                expected: 0x28,
+                found: 0x10
+            })
+        ));
+    }
+
+    #[test]
+    fn test_read_temperature() {
+        let address = Address(0xE800000B1FCD1028);
+        let mut events = vec![];
+        for command in &[0x44, 0xBE] {
+            events.extend(Event::match_rom(&address, *command));
+        }
+        events.extend(
+            [0x91, 0x01, 0x4B, 0x46, 0x7F, 0xFF, 0x0F, 0x10, 0x25]
+                .iter()
+                .map(|byte| Event::Read(*byte)),
+        );
+        let bus = ReplayBus::new(events);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let sensor = Ds18b20::new::<()>(address).unwrap();
+        sensor.start_conversion(&mut one_wire, &mut delay).unwrap();
+        assert_eq!(
+            sensor.read_temperature(&mut one_wire, &mut delay).unwrap(),
+            25_062
+        );
+        bus.finish();
+    }
+
+    #[test]
+    fn test_resolution() {
+        let all = [
+            (Resolution::Bits9, 0x1F, 93_750),
+            (Resolution::Bits10, 0x3F, 187_500),
+            (Resolution::Bits11, 0x5F, 375_000),
+            (Resolution::Bits12, 0x7F, 750_000),
+        ];
+        for (resolution, config, time) in &all {
+            assert_eq!(resolution.config_byte(), *config);
+            assert_eq!(Resolution::from_config_byte(*config), *resolution);
+            assert_eq!(conversion_time(*resolution), *time);
+        }
+    }
+
+    #[test]
+    fn test_set_resolution() {
+        let address = Address(0xE800000B1FCD1028);
+        let mut events = Event::match_rom(&address, 0xBE);
+        events.extend(
+            [0x91, 0x01, 0x4B, 0x46, 0x7F, 0xFF, 0x0F, 0x10, 0x25]
+                .iter()
+                .map(|byte| Event::Read(*byte)),
+        );
+        events.extend(Event::match_rom(&address, 0x4E));
+        events.extend(&[Event::Write(0x4B), Event::Write(0x46), Event::Write(0x3F)]);
+        events.extend(Event::match_rom(&address, 0x48));
+        let bus = ReplayBus::new(events);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let sensor = Ds18b20::new::<()>(address).unwrap();
+        sensor
+            .set_resolution(&mut one_wire, Resolution::Bits10, true, &mut bus.delay())
+            .unwrap();
+        bus.finish();
+    }
+
+    #[test]
+    fn test_alarm_thresholds() {
+        let address = Address(0xE800000B1FCD1028);
+        let read_scratchpad = |events: &mut Vec<Event>, scratchpad: [u8; 9]| {
+            events.extend(Event::match_rom(&address, 0xBE));
+            events.extend(scratchpad.iter().map(|byte| Event::Read(*byte)));
+        };
+        let mut events = vec![];
+        read_scratchpad(
+            &mut events,
+            [0x91, 0x01, 0x4B, 0x46, 0x7F, 0xFF, 0x0F, 0x10, 0x25],
+        );
+        // high 30°C, low -10°C, and the configuration is kept
+        events.extend(Event::match_rom(&address, 0x4E));
+        events.extend(&[Event::Write(0x1E), Event::Write(0xF6), Event::Write(0x7F)]);
+        read_scratchpad(
+            &mut events,
+            [0x91, 0x01, 0x1E, 0xF6, 0x7F, 0xFF, 0x0F, 0x10, 0xAF],
+        );
+        let bus = ReplayBus::new(events);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let sensor = Ds18b20::new::<()>(address).unwrap();
+        sensor
+            .set_alarm_thresholds(&mut one_wire, 30, -10, false, &mut delay)
+            .unwrap();
+        assert_eq!(
+            sensor.alarm_thresholds(&mut one_wire, &mut delay).unwrap(),
+            (30, -10)
+        );
+        bus.finish();
+    }
+
+    #[test]
+    fn test_convert_all() {
+        let bus = ReplayBus::new(vec![
+            Event::Reset { present: true },
+            Event::Write(0xCC),
+            Event::Write(0x44),
+            // still converting for 2 bytes and 7 bits of read slots
+            Event::Read(0x00),
+            Event::Read(0x00),
+            Event::Read(0x80),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        convert_all(&mut one_wire, &mut delay).unwrap();
+        wait_conversion_done(&mut one_wire, &mut delay).unwrap();
+        bus.finish();
+    }
+}
@@ -0,0 +1,48 @@
+//! The DS2401 and DS2411 silicon serial numbers, which have no memory or functions beyond their ROM
+
+use crate::{Address, OneWireResult};
+
+/// The family code of a DS2401 or DS2411
+pub const FAMILY_CODE: u8 = 0x01;
+
+/// A DS2401 or DS2411 silicon serial number
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Ds2401 {
+    address: Address,
+}
+
+impl Ds2401 {
+    /// Returns `WrongFamily` if the address isn't a DS2401 or DS2411
+    pub fn new<E>(address: Address) -> OneWireResult<Ds2401, E> {
+        address.expect_family(FAMILY_CODE)?;
+        Ok(Ds2401 { address })
+    }
+
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// The 48-bit serial number
+    pub fn serial_number(&self) -> u64 {
+        self.address.serial_number()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ds2401::Ds2401;
+    use crate::{Address, OneWireError};
+
+    #[test]
+    fn test_new() {
+        let serial = Ds2401::new::<()>(Address(0x5A00123456789A01)).unwrap();
+        assert_eq!(serial.serial_number(), 0x00123456789A);
+        assert!(matches!(
+            Ds2401::new::<()>(Address(0xE800000B1FCD1028)),
+            Err(OneWireError::WrongFamily {
+                expected: 0x01,
+                found: 0x28
+            })
+        ));
+    }
+}
@@ -0,0 +1,190 @@
+//! A driver for the DS2408 eight channel addressable switch
+
+use crate::{crc, Address, OneWire, OneWireError, OneWireResult};
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// The family code of a DS2408
+pub const FAMILY_CODE: u8 = 0x29;
+
+const READ_PIO_REGISTERS: u8 = 0xF0;
+const CHANNEL_ACCESS_WRITE: u8 = 0x5A;
+
+/// Sent by the device after a successful CHANNEL_ACCESS_WRITE
+const WRITE_CONFIRMATION: u8 = 0xAA;
+
+/// The address of the first register (the PIO logic state)
+const REGISTERS_ADDRESS: u16 = 0x0088;
+
+/// A DS2408 at a known address
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Ds2408 {
+    address: Address,
+}
+
+impl Ds2408 {
+    /// Returns `WrongFamily` if the address isn't a DS2408
+    pub fn new<E>(address: Address) -> OneWireResult<Ds2408, E> {
+        address.expect_family(FAMILY_CODE)?;
+        Ok(Ds2408 { address })
+    }
+
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Reads the 8 registers from 0x88 to 0x8F with READ_PIO_REGISTERS: the PIO logic state,
+    /// output latch, activity latch, conditional search channel selection and polarity, and the
+    /// control/status register, followed by 2 unused bytes.
+    /// Returns `Crc16Mismatch` if the crc16 at the end of the frame is wrong
+    pub fn read_registers<T, E>(
+        &self,
+        onewire: &mut OneWire<T>,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<[u8; 8], E>
+    where
+        T: InputPin<Error = E>,
+        T: OutputPin<Error = E>,
+    {
+        let [address_low, address_high] = REGISTERS_ADDRESS.to_le_bytes();
+        let mut frame = [0; 13];
+        frame[..3].copy_from_slice(&[READ_PIO_REGISTERS, address_low, address_high]);
+        onewire.send_command(READ_PIO_REGISTERS, Some(&self.address), delay)?;
+        onewire.write_bytes(&frame[1..3], delay)?;
+        onewire.read_bytes(&mut frame[3..], delay)?;
+        onewire.end_transaction(delay)?;
+        crc::check_crc16(&frame, 0)?;
+        let mut registers = [0; 8];
+        registers.copy_from_slice(&frame[3..11]);
+        Ok(registers)
+    }
+
+    /// Reads the logic state of the 8 PIO pins
+    pub fn read_pio<T, E>(
+        &self,
+        onewire: &mut OneWire<T>,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<u8, E>
+    where
+        T: InputPin<Error = E>,
+        T: OutputPin<Error = E>,
+    {
+        Ok(self.read_registers(onewire, delay)?[0])
+    }
+
+    /// Reads the control/status register
+    pub fn read_control_status<T, E>(
+        &self,
+        onewire: &mut OneWire<T>,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<u8, E>
+    where
+        T: InputPin<Error = E>,
+        T: OutputPin<Error = E>,
+    {
+        Ok(self.read_registers(onewire, delay)?[5])
+    }
+
+    /// Sets the output latches of the 8 PIO pins with CHANNEL_ACCESS_WRITE. A 1 turns the output
+    /// transistor off, and a 0 pulls the pin low.
+    /// Returns `UnexpectedResponse` if the device doesn't confirm the write, such as when the byte
+    /// was corrupted on the bus
+    pub fn write_pio<T, E>(
+        &self,
+        onewire: &mut OneWire<T>,
+        value: u8,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E>
+    where
+        T: InputPin<Error = E>,
+        T: OutputPin<Error = E>,
+    {
+        onewire.send_command(CHANNEL_ACCESS_WRITE, Some(&self.address), delay)?;
+        onewire.write_bytes(&[value, !value], delay)?;
+        let confirmation = onewire.read_byte(delay)?;
+        onewire.end_transaction(delay)?;
+        if confirmation != WRITE_CONFIRMATION {
+            return Err(OneWireError::UnexpectedResponse);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use crate::ds2408::Ds2408;
+    use crate::mock::{Event, ReplayBus};
+    use crate::{Address, OneWire, OneWireError};
+
+    const ADDRESS: Address = Address(0x0B00000B20687E29);
+
+    #[test]
+    fn test_read_registers() {
+        let replay = |crc: [u8; 2]| {
+            let mut events = Event::match_rom(&ADDRESS, 0xF0);
+            events.extend(&[Event::Write(0x88), Event::Write(0x00)]);
+            events.extend(
+                [
+                    0xA5, 0xFF, 0x00, 0x00, 0x00, 0x88, 0xFF, 0xFF, crc[0], crc[1],
+                ]
+                .iter()
+                .map(|byte| Event::Read(*byte)),
+            );
+            ReplayBus::new(events)
+        };
+        let switch = Ds2408::new::<()>(ADDRESS).unwrap();
+
+        let bus = replay([0x3E, 0x2C]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        assert_eq!(switch.read_pio(&mut one_wire, &mut delay).unwrap(), 0xA5);
+        bus.finish();
+
+        let bus = replay([0x3E, 0x2C]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        assert_eq!(
+            switch
+                .read_control_status(&mut one_wire, &mut delay)
+                .unwrap(),
+            0x88
+        );
+
+        let bus = replay([0x3F, 0x2C]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        assert!(matches!(
+            switch.read_pio(&mut one_wire, &mut bus.delay()),
+            Err(OneWireError::Crc16Mismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_write_pio() {
+        let replay = |confirmation: u8| {
+            let mut events = Event::match_rom(&ADDRESS, 0x5A);
+            events.extend(&[
+                Event::Write(0x0F),
+                Event::Write(0xF0),
+                Event::Read(confirmation),
+            ]);
+            ReplayBus::new(events)
+        };
+        let switch = Ds2408::new::<()>(ADDRESS).unwrap();
+
+        let bus = replay(0xAA);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        switch
+            .write_pio(&mut one_wire, 0x0F, &mut bus.delay())
+            .unwrap();
+        bus.finish();
+
+        let bus = replay(0x00);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        assert!(matches!(
+            switch.write_pio(&mut one_wire, 0x0F, &mut bus.delay()),
+            Err(OneWireError::UnexpectedResponse)
+        ));
+    }
+}
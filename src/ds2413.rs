@@ -0,0 +1,173 @@
+//! A driver for the DS2413 dual channel open-drain switch
+
+use crate::{Address, OneWire, OneWireError, OneWireResult};
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// The family code of a DS2413
+pub const FAMILY_CODE: u8 = 0x3A;
+
+const PIO_ACCESS_READ: u8 = 0xF5;
+const PIO_ACCESS_WRITE: u8 = 0x5A;
+
+/// Sent by the device after a successful PIO_ACCESS_WRITE
+const WRITE_CONFIRMATION: u8 = 0xAA;
+
+/// The state of both channels. A latch of `true` turns the output transistor off, so the pin can
+/// be pulled high (or driven by something else), and `false` pulls the pin low. The sense is the
+/// level of the pin
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PioState {
+    pub a_sense: bool,
+    pub a_latch: bool,
+    pub b_sense: bool,
+    pub b_latch: bool,
+}
+
+impl PioState {
+    /// Decodes the status byte sent for PIO_ACCESS_READ. The upper 4 bits are the complement of the
+    /// lower 4, and `UnexpectedResponse` is returned if they don't match
+    pub fn from_status<E>(status: u8) -> OneWireResult<PioState, E> {
+        if status >> 4 != !status & 0x0F {
+            return Err(OneWireError::UnexpectedResponse);
+        }
+        Ok(PioState {
+            a_sense: status & 0x01 != 0,
+            a_latch: status & 0x02 != 0,
+            b_sense: status & 0x04 != 0,
+            b_latch: status & 0x08 != 0,
+        })
+    }
+
+    /// The byte sent for PIO_ACCESS_WRITE, which sets the latches. The unused bits must be 1
+    pub fn latch_byte(&self) -> u8 {
+        0xFC | (self.a_latch as u8) | ((self.b_latch as u8) << 1)
+    }
+}
+
+/// A DS2413 at a known address
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Ds2413 {
+    address: Address,
+}
+
+impl Ds2413 {
+    /// Returns `WrongFamily` if the address isn't a DS2413
+    pub fn new<E>(address: Address) -> OneWireResult<Ds2413, E> {
+        address.expect_family(FAMILY_CODE)?;
+        Ok(Ds2413 { address })
+    }
+
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Reads the latch and sense of both channels with PIO_ACCESS_READ
+    pub fn read_state<T, E>(
+        &self,
+        onewire: &mut OneWire<T>,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<PioState, E>
+    where
+        T: InputPin<Error = E>,
+        T: OutputPin<Error = E>,
+    {
+        onewire.send_command(PIO_ACCESS_READ, Some(&self.address), delay)?;
+        let status = onewire.read_byte(delay)?;
+        onewire.end_transaction(delay)?;
+        PioState::from_status(status)
+    }
+
+    /// Sets the latches of both channels with PIO_ACCESS_WRITE (the senses of `state` are
+    /// ignored). Returns `UnexpectedResponse` if the device doesn't confirm the write, such as when
+    /// the byte was corrupted on the bus
+    pub fn write_state<T, E>(
+        &self,
+        onewire: &mut OneWire<T>,
+        state: PioState,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E>
+    where
+        T: InputPin<Error = E>,
+        T: OutputPin<Error = E>,
+    {
+        let latches = state.latch_byte();
+        onewire.send_command(PIO_ACCESS_WRITE, Some(&self.address), delay)?;
+        onewire.write_bytes(&[latches, !latches], delay)?;
+        let confirmation = onewire.read_byte(delay)?;
+        onewire.end_transaction(delay)?;
+        if confirmation != WRITE_CONFIRMATION {
+            return Err(OneWireError::UnexpectedResponse);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use crate::ds2413::{Ds2413, PioState};
+    use crate::mock::{Event, ReplayBus};
+    use crate::{Address, OneWire, OneWireError};
+
+    #[test]
+    fn test_pio_state() {
+        assert_eq!(
+            PioState::from_status::<()>(0x96).unwrap(),
+            PioState {
+                a_sense: false,
+                a_latch: true,
+                b_sense: true,
+                b_latch: false,
+            }
+        );
+        assert!(matches!(
+            PioState::from_status::<()>(0x06),
+            Err(OneWireError::UnexpectedResponse)
+        ));
+
+        let state = PioState {
+            a_sense: false,
+            a_latch: false,
+            b_sense: false,
+            b_latch: true,
+        };
+        assert_eq!(state.latch_byte(), 0xFE);
+    }
+
+    #[test]
+    fn test_write_state() {
+        let address = Address(0x0B00000B20687E3A);
+        let replay = |confirmation: u8| {
+            let mut events = Event::match_rom(&address, 0x5A);
+            events.extend(&[
+                Event::Write(0xFD),
+                Event::Write(0x02),
+                Event::Read(confirmation),
+            ]);
+            ReplayBus::new(events)
+        };
+        let state = PioState {
+            a_sense: false,
+            a_latch: true,
+            b_sense: false,
+            b_latch: false,
+        };
+        let switch = Ds2413::new::<()>(address).unwrap();
+
+        let bus = replay(0xAA);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        switch
+            .write_state(&mut one_wire, state, &mut bus.delay())
+            .unwrap();
+        bus.finish();
+
+        let bus = replay(0xFF);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        assert!(matches!(
+            switch.write_state(&mut one_wire, state, &mut bus.delay()),
+            Err(OneWireError::UnexpectedResponse)
+        ));
+    }
+}
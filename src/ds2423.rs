@@ -0,0 +1,126 @@
+//! A driver for the DS2423 RAM with counters, such as for pulse counting
+
+use crate::{crc, Address, OneWire, OneWireResult};
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// The family code of a DS2423
+pub const FAMILY_CODE: u8 = 0x1D;
+
+const READ_MEMORY_COUNTER: u8 = 0xA5;
+
+/// The length of a READ_MEMORY_COUNTER frame from the start of a page: the command, target
+/// address, 32 bytes of the page, the counter, 4 zero bytes, and the inverted crc16
+const FRAME_LEN: usize = 45;
+
+/// The position of the counter in a frame
+const COUNTER_OFFSET: usize = 35;
+
+/// The two external counters, which count the falling edges on the A and B pins.
+/// Each is attached to a page of memory, and is read along with the page
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Counter {
+    /// Counts the A pin, attached to page 14
+    A,
+    /// Counts the B pin, attached to page 15
+    B,
+}
+
+impl Counter {
+    /// The memory address of the start of the page that the counter is attached to
+    fn page_address(&self) -> u16 {
+        match self {
+            Counter::A => 0x01C0,
+            Counter::B => 0x01E0,
+        }
+    }
+}
+
+/// A DS2423 at a known address
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Ds2423 {
+    address: Address,
+}
+
+impl Ds2423 {
+    /// Returns `WrongFamily` if the address isn't a DS2423
+    pub fn new<E>(address: Address) -> OneWireResult<Ds2423, E> {
+        address.expect_family(FAMILY_CODE)?;
+        Ok(Ds2423 { address })
+    }
+
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Reads a counter with READ_MEMORY_COUNTER, from the start of its page.
+    /// Returns `Crc16Mismatch` if the crc16 at the end of the frame is wrong
+    pub fn read_counter<T, E>(
+        &self,
+        onewire: &mut OneWire<T>,
+        counter: Counter,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<u32, E>
+    where
+        T: InputPin<Error = E>,
+        T: OutputPin<Error = E>,
+    {
+        let [address_low, address_high] = counter.page_address().to_le_bytes();
+        let mut frame = [0; FRAME_LEN];
+        frame[..3].copy_from_slice(&[READ_MEMORY_COUNTER, address_low, address_high]);
+        onewire.send_command(READ_MEMORY_COUNTER, Some(&self.address), delay)?;
+        onewire.write_bytes(&frame[1..3], delay)?;
+        onewire.read_bytes(&mut frame[3..], delay)?;
+        onewire.end_transaction(delay)?;
+        crc::check_crc16(&frame, 0)?;
+        let mut value = [0; 4];
+        value.copy_from_slice(&frame[COUNTER_OFFSET..COUNTER_OFFSET + 4]);
+        Ok(u32::from_le_bytes(value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use crate::ds2423::{Counter, Ds2423};
+    use crate::mock::{Event, ReplayBus};
+    use crate::{Address, OneWire, OneWireError};
+
+    #[test]
+    fn test_read_counter() {
+        let address = Address(0x0B00000B20687E1D);
+        let replay = |crc: [u8; 2]| {
+            let mut events = Event::match_rom(&address, 0xA5);
+            events.extend(&[Event::Write(0xC0), Event::Write(0x01)]);
+            // an erased page, a count of 12345, the 4 zero bytes, then the crc
+            events.extend((0..32).map(|_| Event::Read(0xFF)));
+            events.extend(
+                [
+                    0x39, 0x30, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, crc[0], crc[1],
+                ]
+                .iter()
+                .map(|byte| Event::Read(*byte)),
+            );
+            ReplayBus::new(events)
+        };
+        let counter = Ds2423::new::<()>(address).unwrap();
+
+        let bus = replay([0x05, 0x67]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        assert_eq!(
+            counter
+                .read_counter(&mut one_wire, Counter::A, &mut bus.delay())
+                .unwrap(),
+            12345
+        );
+        bus.finish();
+
+        let bus = replay([0x05, 0x66]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        assert!(matches!(
+            counter.read_counter(&mut one_wire, Counter::A, &mut bus.delay()),
+            Err(OneWireError::Crc16Mismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,202 @@
+//! A driver for the DS2431 1024-bit EEPROM
+
+use crate::{
+    crc, delay_us_long, Address, OneWire, OneWireError, OneWireResult, COPY_SCRATCHPAD_US,
+};
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// The family code of a DS2431
+pub const FAMILY_CODE: u8 = 0x2D;
+
+/// The size of a row, which is written at once through the scratchpad
+pub const ROW_LEN: usize = 8;
+
+const READ_MEMORY: u8 = 0xF0;
+const WRITE_SCRATCHPAD: u8 = 0x0F;
+const READ_SCRATCHPAD: u8 = 0xAA;
+const COPY_SCRATCHPAD: u8 = 0x55;
+
+/// The ending offset and status of a scratchpad holding a complete row, with no errors
+const COMPLETE_ROW_STATUS: u8 = 0x07;
+
+/// Sent by the device after a successful COPY_SCRATCHPAD
+const COPY_CONFIRMATION: u8 = 0xAA;
+
+/// A DS2431 at a known address
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Ds2431 {
+    address: Address,
+}
+
+impl Ds2431 {
+    /// Returns `WrongFamily` if the address isn't a DS2431
+    pub fn new<E>(address: Address) -> OneWireResult<Ds2431, E> {
+        address.expect_family(FAMILY_CODE)?;
+        Ok(Ds2431 { address })
+    }
+
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Reads memory starting at `memory_address` into `output` with READ_MEMORY
+    pub fn read_memory<T, E>(
+        &self,
+        onewire: &mut OneWire<T>,
+        memory_address: u16,
+        output: &mut [u8],
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E>
+    where
+        T: InputPin<Error = E>,
+        T: OutputPin<Error = E>,
+    {
+        onewire.send_command(READ_MEMORY, Some(&self.address), delay)?;
+        onewire.write_bytes(&memory_address.to_le_bytes(), delay)?;
+        onewire.read_bytes(output, delay)?;
+        onewire.end_transaction(delay)
+    }
+
+    /// Writes a row of memory at `memory_address`, which must be the start of a row (a multiple of
+    /// 8), returning `MisalignedAddress` if not.
+    /// The row is written to the scratchpad, read back to check that it was received correctly,
+    /// then copied to EEPROM. Returns `Crc16Mismatch` if a crc16 from the device is wrong, or
+    /// `UnexpectedResponse` if the scratchpad doesn't hold the row or the copy isn't confirmed
+    pub fn write_row<T, E>(
+        &self,
+        onewire: &mut OneWire<T>,
+        memory_address: u16,
+        row: &[u8; ROW_LEN],
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E>
+    where
+        T: InputPin<Error = E>,
+        T: OutputPin<Error = E>,
+    {
+        if usize::from(memory_address) % ROW_LEN != 0 {
+            return Err(OneWireError::MisalignedAddress);
+        }
+        let [address_low, address_high] = memory_address.to_le_bytes();
+
+        // the command, target address, row, and crc16
+        let mut frame = [0; 13];
+        frame[..3].copy_from_slice(&[WRITE_SCRATCHPAD, address_low, address_high]);
+        frame[3..11].copy_from_slice(row);
+        onewire.send_command(WRITE_SCRATCHPAD, Some(&self.address), delay)?;
+        onewire.write_bytes(&frame[1..11], delay)?;
+        onewire.read_bytes(&mut frame[11..], delay)?;
+        onewire.end_transaction(delay)?;
+        crc::check_crc16(&frame, 0)?;
+
+        // the command, target address, ending offset and status, row, and crc16
+        let mut readback = [0; 14];
+        readback[0] = READ_SCRATCHPAD;
+        onewire.send_command(READ_SCRATCHPAD, Some(&self.address), delay)?;
+        onewire.read_bytes(&mut readback[1..], delay)?;
+        onewire.end_transaction(delay)?;
+        crc::check_crc16(&readback, 0)?;
+        if readback[1..4] != [address_low, address_high, COMPLETE_ROW_STATUS]
+            || readback[4..12] != row[..]
+        {
+            return Err(OneWireError::UnexpectedResponse);
+        }
+
+        // the target address and status authorize the copy
+        onewire.send_command(COPY_SCRATCHPAD, Some(&self.address), delay)?;
+        onewire.write_bytes(&readback[1..4], delay)?;
+        delay_us_long(delay, COPY_SCRATCHPAD_US);
+        let confirmation = onewire.read_byte(delay)?;
+        onewire.end_transaction(delay)?;
+        if confirmation != COPY_CONFIRMATION {
+            return Err(OneWireError::UnexpectedResponse);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use crate::ds2431::Ds2431;
+    use crate::mock::{Event, ReplayBus};
+    use crate::{Address, OneWire, OneWireError};
+    use std::vec;
+    use std::vec::Vec;
+
+    const ADDRESS: Address = Address(0x0B00000B20687E2D);
+
+    #[test]
+    fn test_read_memory() {
+        let mut events = Event::match_rom(&ADDRESS, 0xF0);
+        events.extend(&[
+            Event::Write(0x10),
+            Event::Write(0x00),
+            Event::Read(0x12),
+            Event::Read(0x34),
+        ]);
+        let bus = ReplayBus::new(events);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let eeprom = Ds2431::new::<()>(ADDRESS).unwrap();
+        let mut output = [0; 2];
+        eeprom
+            .read_memory(&mut one_wire, 0x0010, &mut output, &mut bus.delay())
+            .unwrap();
+        assert_eq!(output, [0x12, 0x34]);
+        bus.finish();
+    }
+
+    /// Writing row 1 (1 to 8) to the scratchpad, then reading back `readback` with `crc`
+    fn write_scratchpad_events(readback: [u8; 8], crc: [u8; 2]) -> Vec<Event> {
+        let mut events = Event::match_rom(&ADDRESS, 0x0F);
+        events.extend(&[Event::Write(0x08), Event::Write(0x00)]);
+        events.extend((1..=8).map(Event::Write));
+        events.extend(&[Event::Read(0xBE), Event::Read(0xC5)]);
+        events.extend(Event::match_rom(&ADDRESS, 0xAA));
+        events.extend(&[Event::Read(0x08), Event::Read(0x00), Event::Read(0x07)]);
+        events.extend(readback.iter().map(|byte| Event::Read(*byte)));
+        events.extend(&[Event::Read(crc[0]), Event::Read(crc[1])]);
+        events
+    }
+
+    #[test]
+    fn test_write_row() {
+        let row = [1, 2, 3, 4, 5, 6, 7, 8];
+        let eeprom = Ds2431::new::<()>(ADDRESS).unwrap();
+
+        let mut events = write_scratchpad_events(row, [0x98, 0xB2]);
+        events.extend(Event::match_rom(&ADDRESS, 0x55));
+        events.extend(&[
+            Event::Write(0x08),
+            Event::Write(0x00),
+            Event::Write(0x07),
+            Event::Read(0xAA),
+        ]);
+        let bus = ReplayBus::new(events);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        eeprom
+            .write_row(&mut one_wire, 0x0008, &row, &mut bus.delay())
+            .unwrap();
+        bus.finish();
+
+        // the scratchpad doesn't hold the row, but the crc of what was read back is correct
+        let bus = ReplayBus::new(write_scratchpad_events(
+            [0, 2, 3, 4, 5, 6, 7, 8],
+            [0x59, 0x7E],
+        ));
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        assert!(matches!(
+            eeprom.write_row(&mut one_wire, 0x0008, &row, &mut bus.delay()),
+            Err(OneWireError::UnexpectedResponse)
+        ));
+        bus.finish();
+
+        let bus = ReplayBus::new(vec![]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        assert!(matches!(
+            eeprom.write_row(&mut one_wire, 0x0009, &row, &mut bus.delay()),
+            Err(OneWireError::MisalignedAddress)
+        ));
+    }
+}
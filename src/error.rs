@@ -1,19 +1,189 @@
+// the derived impls and `Display` still have to name the deprecated `FamilyCodeMismatch`
+#![allow(deprecated)]
+
 use core::fmt::Debug;
 
 pub type OneWireResult<T, E> = Result<T, OneWireError<E>>;
 
+/// The part of a transaction that was in progress when an error occurred
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Phase {
+    /// Sending a reset pulse, or detecting the presence pulse
+    Reset,
+
+    /// Sending a ROM command (and address) to select devices
+    Address,
+
+    /// Sending a function command to the selected devices
+    Command,
+
+    /// Reading data from a device
+    DataRead,
+
+    /// Writing data to a device
+    DataWrite,
+
+    /// Searching for device addresses
+    Search,
+}
+
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum OneWireError<E> {
-    /// The Bus was expected to be pulled high by a ~5K ohm pull-up resistor, but it wasn't
+    /// The Bus was expected to be pulled high by a ~5K ohm pull-up resistor, but it wasn't.
+    /// This is reserved for protocol faults, such as a device still holding its presence pulse at
+    /// the end of the reset recovery time. A bus that is only too slow to rise returns `Timeout`
     BusNotHigh,
 
-    PinError(E),
+    /// The pin returned an error, during the given phase of a transaction
+    PinError { error: E, phase: Phase },
+
+    /// The bus was still held low by something else when waiting for it to become idle
+    BusBusy,
+
+    /// Another master was using the bus at the same time
+    BusContention,
+
+    /// The bus was held low when the bus was created, and still didn't rise after a reset pulse
+    BusStuckLow,
+
+    /// The bus stayed low when nothing should have been holding it low, such as before a reset
+    /// pulse or at the end of a read slot, and was still low well after the recovery timeout.
+    /// This is usually a short circuit, or a faulty device
+    BusShortCircuit,
 
     /// An unexpected response was received from a command. This generally happens when a new sensor is added
     /// or removed from the bus during a command, such as a device search.
     UnexpectedResponse,
 
+    /// A device had to be addressed again, but none has been selected (or the last ROM command was
+    /// `skip_address`). Returned by `resume` and `send_command_resume`, and by reads and writes
+    /// when `reset_per_byte` is enabled
+    NoDeviceSelected,
+
+    /// No device responded to a reset pulse
+    NoDevicePresent,
+
+    /// More than one device was found on a bus that was expected to only have a single device
+    MultipleDevices,
+
+    /// The provided buffer is too small to hold the result
+    BufferTooSmall,
+
+    /// A string couldn't be parsed as an address
+    InvalidAddress,
+
+    /// A saved `SearchState` couldn't be restored
+    InvalidSearchState,
+
+    /// A memory address wasn't aligned to the start of a row or page, as the operation requires
+    MisalignedAddress,
+
+    /// The device has a different family code than expected
+    WrongFamily { expected: u8, found: u8 },
+
+    /// The device has a different family code than expected
+    #[deprecated(note = "use `WrongFamily`, which includes the expected and found family codes")]
     FamilyCodeMismatch,
-    CrcMismatch,
+
+    /// The crc of received data was wrong. `computed` is the crc of the data as received, and
+    /// `expected` is the crc value that was received with it
+    CrcMismatch { computed: u8, expected: u8 },
+
+    /// The crc16 of received data was wrong, the same as `CrcMismatch` for devices that use a crc16
+    Crc16Mismatch { computed: u16, expected: u16 },
+
+    /// The bus didn't rise within the recovery timeout (see `OneWire::set_recovery_timeout`), but
+    /// did rise soon after. The bus is slow or overloaded, such as from a weak pull-up or too much
+    /// capacitance, rather than broken (see `BusShortCircuit`) or a device breaking the protocol
+    /// (see `BusNotHigh`).
+    /// Also returned when a device doesn't finish an operation in time
     Timeout,
 }
+
+impl<E> core::fmt::Display for OneWireError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            OneWireError::BusNotHigh => f.write_str("bus was not pulled high"),
+            OneWireError::PinError { phase, .. } => write!(f, "pin error during {:?}", phase),
+            OneWireError::BusBusy => f.write_str("bus was held low by something else"),
+            OneWireError::BusContention => f.write_str("another master was using the bus"),
+            OneWireError::BusStuckLow => f.write_str("bus is stuck low"),
+            OneWireError::BusShortCircuit => f.write_str("bus is shorted low"),
+            OneWireError::UnexpectedResponse => f.write_str("unexpected response from a device"),
+            OneWireError::NoDeviceSelected => f.write_str("no device has been selected"),
+            OneWireError::NoDevicePresent => f.write_str("no device responded to a reset"),
+            OneWireError::MultipleDevices => f.write_str("more than one device is on the bus"),
+            OneWireError::BufferTooSmall => f.write_str("buffer is too small"),
+            OneWireError::InvalidAddress => f.write_str("invalid address"),
+            OneWireError::InvalidSearchState => f.write_str("invalid search state"),
+            OneWireError::MisalignedAddress => f.write_str("misaligned memory address"),
+            OneWireError::WrongFamily { expected, found } => write!(
+                f,
+                "expected family code {:02X}, found {:02X}",
+                expected, found
+            ),
+            OneWireError::FamilyCodeMismatch => f.write_str("family code mismatch"),
+            OneWireError::CrcMismatch { computed, expected } => write!(
+                f,
+                "crc mismatch: computed {:02X}, expected {:02X}",
+                computed, expected
+            ),
+            OneWireError::Crc16Mismatch { computed, expected } => write!(
+                f,
+                "crc16 mismatch: computed {:04X}, expected {:04X}",
+                computed, expected
+            ),
+            OneWireError::Timeout => f.write_str("timed out"),
+        }
+    }
+}
+
+/// The pin error of a `PinError` is its source, so it is reported by error chains rather than
+/// repeated in the `Display` message
+impl<E: core::error::Error + 'static> core::error::Error for OneWireError<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            OneWireError::PinError { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use crate::{OneWireError, Phase};
+    use core::error::Error;
+    use std::string::ToString;
+
+    #[derive(Debug)]
+    struct PinFault;
+
+    impl core::fmt::Display for PinFault {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            f.write_str("pin fault")
+        }
+    }
+
+    impl Error for PinFault {}
+
+    #[test]
+    fn test_error_source() {
+        let error = OneWireError::PinError {
+            error: PinFault,
+            phase: Phase::DataRead,
+        };
+        assert_eq!(error.to_string(), "pin error during DataRead");
+        assert_eq!(error.source().unwrap().to_string(), "pin fault");
+
+        let error: OneWireError<PinFault> = OneWireError::CrcMismatch {
+            computed: 0xE8,
+            expected: 0xE9,
+        };
+        assert_eq!(error.to_string(), "crc mismatch: computed E8, expected E9");
+        assert!(error.source().is_none());
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{ds18b20, ds2401, ds2408, ds2413, ds2423, ds2431};
+
+/// The type of a device, identified by the family code of its address
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Family {
+    /// DS2401 or DS2411 silicon serial number (0x01), see `ds2401`
+    SerialNumber,
+
+    /// DS2423 RAM with counters (0x1D), see `ds2423`
+    Ds2423,
+
+    /// DS18B20 temperature sensor (0x28), see `ds18b20`
+    Ds18b20,
+
+    /// DS2408 eight channel switch (0x29), see `ds2408`
+    Ds2408,
+
+    /// DS2431 EEPROM (0x2D), see `ds2431`
+    Ds2431,
+
+    /// DS2413 dual channel switch (0x3A), see `ds2413`
+    Ds2413,
+
+    /// A family without a driver in this crate
+    Unknown(u8),
+}
+
+impl Family {
+    /// The family code of the devices in this family
+    pub fn code(&self) -> u8 {
+        match self {
+            Family::SerialNumber => ds2401::FAMILY_CODE,
+            Family::Ds2423 => ds2423::FAMILY_CODE,
+            Family::Ds18b20 => ds18b20::FAMILY_CODE,
+            Family::Ds2408 => ds2408::FAMILY_CODE,
+            Family::Ds2431 => ds2431::FAMILY_CODE,
+            Family::Ds2413 => ds2413::FAMILY_CODE,
+            Family::Unknown(code) => *code,
+        }
+    }
+}
+
+impl From<u8> for Family {
+    fn from(code: u8) -> Family {
+        match code {
+            ds2401::FAMILY_CODE => Family::SerialNumber,
+            ds2423::FAMILY_CODE => Family::Ds2423,
+            ds18b20::FAMILY_CODE => Family::Ds18b20,
+            ds2408::FAMILY_CODE => Family::Ds2408,
+            ds2431::FAMILY_CODE => Family::Ds2431,
+            ds2413::FAMILY_CODE => Family::Ds2413,
+            code => Family::Unknown(code),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Address, Family};
+
+    #[test]
+    fn test_family() {
+        assert_eq!(Address(0xE800000B1FCD1028).family(), Family::Ds18b20);
+        assert_eq!(Family::from(0x01), Family::SerialNumber);
+        assert_eq!(Family::from(0x1D), Family::Ds2423);
+        assert_eq!(Family::from(0x29), Family::Ds2408);
+        assert_eq!(Family::from(0x2D), Family::Ds2431);
+        assert_eq!(Family::from(0x3A), Family::Ds2413);
+        assert_eq!(Family::from(0x10), Family::Unknown(0x10));
+        for code in 0..=255 {
+            assert_eq!(Family::from(code).code(), code);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+extern crate alloc;
+
+use crate::{Address, OneWire, OneWireResult};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+impl<T, E> OneWire<T>
+where
+    T: InputPin<Error = E>,
+    T: OutputPin<Error = E>,
+{
+    /// Finds all devices on the bus, and returns a human readable report with one line per device,
+    /// listing its address, family, and whether it is alarming.
+    /// The alarm state comes from a second search made after the device list, so a device that
+    /// changes alarm state, or joins or leaves the bus, between the two searches can be reported
+    /// with the wrong alarm state
+    pub fn inventory_report(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<String, E> {
+        let devices = self.collect_devices_alloc(false, delay)?;
+        let alarming = self.collect_devices_alloc(true, delay)?;
+
+        let mut report = String::new();
+        // writing to a String can't fail
+        let _ = writeln!(report, "Found {} device(s)", devices.len());
+        for address in &devices {
+            let _ = writeln!(
+                report,
+                "{}  family: {:?} ({:#04x})  alarming: {}",
+                address,
+                address.family(),
+                address.family_code(),
+                if alarming.contains(address) {
+                    "yes"
+                } else {
+                    "no"
+                }
+            );
+        }
+        Ok(report)
+    }
+
+    fn collect_devices_alloc(
+        &mut self,
+        only_alarming: bool,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<Vec<Address>, E> {
+        self.devices(only_alarming, delay).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use crate::mock::{MockBus, MockDevice};
+    use crate::OneWire;
+    use std::vec;
+
+    #[test]
+    fn test_inventory_report() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628).alarming(),
+            MockDevice::new(0x28000001B81C0201),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        assert_eq!(
+            one_wire.inventory_report(&mut bus.delay()).unwrap(),
+            "Found 3 device(s)\n\
+             28:10:CD:1F:0B:00:00:E8  family: Ds18b20 (0x28)  alarming: no\n\
+             28:16:85:AC:08:00:00:70  family: Ds18b20 (0x28)  alarming: yes\n\
+             01:02:1C:B8:01:00:00:28  family: SerialNumber (0x01)  alarming: no\n"
+        );
+    }
+}
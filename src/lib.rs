@@ -4,18 +4,72 @@ use embedded_hal::blocking::delay::DelayUs;
 use embedded_hal::digital::v2::{InputPin, OutputPin};
 
 mod address;
+#[cfg(feature = "atomic")]
+mod atomic;
+#[cfg(feature = "heapless")]
+mod collect;
 pub mod commands;
 pub mod crc;
+pub mod ds18b20;
+pub mod ds2401;
+pub mod ds2408;
+pub mod ds2413;
+pub mod ds2423;
+pub mod ds2431;
 mod error;
+mod family;
+#[cfg(feature = "alloc")]
+mod inventory;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+mod selected;
+mod timing;
 
 pub use address::Address;
-pub use error::{OneWireError, OneWireResult};
+pub use error::{OneWireError, OneWireResult, Phase};
+pub use family::Family;
+pub use selected::Selected;
+pub use timing::{Speed, Timing};
 
 pub const READ_SLOT_DURATION_MICROS: u16 = 70;
 
+/// Interval between samples when polling the bus for a level change
+const POLL_INTERVAL_US: u16 = 2;
+
+/// The maximum number of families that can have their own timing with `set_family_timing`
+pub const FAMILY_TIMING_SLOTS: usize = 4;
+
+/// How long a device takes to copy its scratchpad to EEPROM
+const COPY_SCRATCHPAD_US: u32 = 10_000;
+
+/// How much longer than the recovery timeout the bus can stay low before it is reported as shorted.
+/// No device holds the bus low for longer than the longest (extended) reset pulse
+const SHORT_CIRCUIT_US: u32 = 1_000;
+
+/// How many extended reset pulses `OneWire::recover` sends
+const RECOVERY_RESETS: u8 = 3;
+
+/// Delays for a duration that may not fit in a single `DelayUs<u16>` call
+fn delay_us_long(delay: &mut impl DelayUs<u16>, mut us: u32) {
+    while us > 0 {
+        let step = core::cmp::min(us, u16::MAX as u32);
+        delay.delay_us(step as u16);
+        us -= step;
+    }
+}
+
 /// Implementation of the 1-Wire protocol.
 /// https://www.maximintegrated.com/en/design/technical-documents/app-notes/1/126.html
-#[derive(Debug)]
+///
+/// With the `serde` feature, a search state can be saved and restored to continue a search later.
+/// Only the address, discrepancies, and last discrepancy index are saved
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "SavedSearchState", into = "SavedSearchState")
+)]
 pub struct SearchState {
     // The address of the last found device
     address: u64,
@@ -26,10 +80,80 @@ pub struct SearchState {
     // index of the last (leftmost / closest to MSB) discrepancy bit. This can be calculated from the
     // discrepancy bitflags, but it's cheaper to just save it. Index is an offset from the LSB
     last_discrepancy_index: u8,
+
+    // number of bits of the address (from the LSB) that are known, which is less than 64 for a
+    // seeded state or a search pass in progress
+    known_bits: u8,
+}
+
+/// The fields of a `SearchState` that are needed to continue a search
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "SearchState")]
+struct SavedSearchState {
+    address: u64,
+    discrepancies: u64,
+    last_discrepancy_index: u8,
+}
+
+#[cfg(feature = "serde")]
+impl From<SavedSearchState> for SearchState {
+    fn from(saved: SavedSearchState) -> Self {
+        SearchState::from_parts(
+            saved.address,
+            saved.discrepancies,
+            saved.last_discrepancy_index,
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<SearchState> for SavedSearchState {
+    fn from(state: SearchState) -> Self {
+        SavedSearchState {
+            address: state.address,
+            discrepancies: state.discrepancies,
+            last_discrepancy_index: state.last_discrepancy_index,
+        }
+    }
+}
+
+/// The logic level of the bus
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BusLevel {
+    /// Released, and pulled high by the pull-up resistor
+    High,
+
+    /// Driven low by the master or a device
+    Low,
 }
 
 pub struct OneWire<T> {
     pin: T,
+    speed: Speed,
+    timing: Timing,
+    overdrive_timing: Timing,
+
+    // timing used when addressing a device of a specific family at a specific speed
+    family_timing: [Option<(u8, Speed, Timing)>; FAMILY_TIMING_SLOTS],
+
+    // the family timing in use until the next reset, after addressing a device
+    active_family_timing: Option<Timing>,
+
+    // how long to wait for the bus to be pulled high before giving up
+    recovery_timeout_us: u16,
+
+    // the part of the transaction in progress, reported with pin errors
+    phase: Phase,
+
+    // the device most recently addressed with `match_address`
+    selected: Option<Address>,
+
+    // re-address the selected device before every byte of `read_bytes` / `write_bytes`
+    reset_per_byte: bool,
+
+    // accept device addresses (ROM codes) that fail the crc check
+    skip_crc_check: bool,
 }
 
 impl<T, E> OneWire<T>
@@ -38,80 +162,453 @@ where
     T: OutputPin<Error = E>,
 {
     pub fn new(pin: T) -> OneWireResult<OneWire<T>, E> {
-        let mut one_wire = OneWire { pin };
+        Self::with_timing(pin, Timing::default())
+    }
+
+    /// Creates a bus that uses custom standard speed timing instead of the Maxim recommended defaults
+    pub fn with_timing(pin: T, timing: Timing) -> OneWireResult<OneWire<T>, E> {
+        let mut one_wire = OneWire {
+            pin,
+            speed: Speed::Standard,
+            timing,
+            overdrive_timing: Timing::OVERDRIVE,
+            family_timing: [None; FAMILY_TIMING_SLOTS],
+            active_family_timing: None,
+            recovery_timeout_us: 250,
+            phase: Phase::Reset,
+            selected: None,
+            reset_per_byte: false,
+            skip_crc_check: false,
+        };
         // Pin should be high during idle.
         one_wire.release_bus()?;
         Ok(one_wire)
     }
 
+    /// Creates a bus, and if the bus is being held low (such as by a device that was interrupted
+    /// part way through a transaction at power up), sends a reset pulse and waits for the bus to
+    /// rise. Returns `BusStuckLow` if the bus is still low after the reset
+    pub fn new_with_recovery(
+        pin: T,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<OneWire<T>, E> {
+        let mut one_wire = Self::new(pin)?;
+        if one_wire.is_bus_low()? {
+            let timing = *one_wire.timing();
+            one_wire.set_bus_low()?;
+            delay.delay_us(timing.reset_low_us);
+            one_wire.release_bus()?;
+            // devices may send a presence pulse, which ends within the reset recovery time
+            delay.delay_us(timing.reset_recovery_us);
+            match one_wire.wait_for_idle(u32::from(one_wire.recovery_timeout_us), delay) {
+                Err(OneWireError::BusBusy) => return Err(OneWireError::BusStuckLow),
+                result => result?,
+            }
+        }
+        Ok(one_wire)
+    }
+
     pub fn into_inner(self) -> T {
         self.pin
     }
 
+    /// Changes the speed used for all communication on the bus.
+    /// This doesn't send anything to the devices, so they must already be at the new speed
+    /// (a standard speed reset returns all devices to standard speed)
+    pub fn set_speed(&mut self, speed: Speed) {
+        self.speed = speed;
+        // family timing only applies at the speed it was set for
+        self.active_family_timing = None;
+    }
+
+    /// Sets how long (in microseconds) to wait for the bus to be pulled high by the pull-up resistor
+    /// before returning `Timeout`, such as before a reset. The default is 250µs.
+    /// A bus with a weak pull-up and a lot of capacitance may need longer, but a long timeout also
+    /// takes longer to report a bus that is stuck low
+    pub fn set_recovery_timeout(&mut self, timeout_us: u16) {
+        self.recovery_timeout_us = timeout_us;
+    }
+
+    /// The speed currently used for communication on the bus
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+
+    /// Changes the timing used at the given speed, such as a longer reset pulse for a long bus.
+    /// The new timing is used from the next operation at that speed
+    pub fn set_timing(&mut self, speed: Speed, timing: Timing) {
+        match speed {
+            Speed::Standard => self.timing = timing,
+            Speed::Overdrive => self.overdrive_timing = timing,
+        }
+    }
+
+    /// Uses `timing` instead of the bus timing after addressing a device of the given family with
+    /// `match_address` at the given speed, until the next reset. This allows relaxed timing to be
+    /// used only for slow devices on a bus that is otherwise shared with faster devices.
+    /// Returns `BufferTooSmall` if `FAMILY_TIMING_SLOTS` other families (or speeds) already have
+    /// their own timing
+    pub fn set_family_timing(
+        &mut self,
+        family: u8,
+        speed: Speed,
+        timing: Timing,
+    ) -> OneWireResult<(), E> {
+        let slot = self
+            .family_timing
+            .iter()
+            .position(|slot| {
+                matches!(slot, Some((slot_family, slot_speed, _))
+                    if *slot_family == family && *slot_speed == speed)
+            })
+            .or_else(|| self.family_timing.iter().position(|slot| slot.is_none()))
+            .ok_or(OneWireError::BufferTooSmall)?;
+        self.family_timing[slot] = Some((family, speed, timing));
+        Ok(())
+    }
+
+    /// Removes the timing set for a family with `set_family_timing`, at both speeds
+    pub fn clear_family_timing(&mut self, family: u8) {
+        for slot in self.family_timing.iter_mut() {
+            if matches!(slot, Some((slot_family, _, _)) if *slot_family == family) {
+                *slot = None;
+            }
+        }
+    }
+
+    /// The timing currently used for communication on the bus. This is the timing for the current
+    /// speed, or the family timing (see `set_family_timing`) of the addressed device
+    pub fn timing(&self) -> &Timing {
+        if let Some(timing) = &self.active_family_timing {
+            return timing;
+        }
+        match self.speed {
+            Speed::Standard => &self.timing,
+            Speed::Overdrive => &self.overdrive_timing,
+        }
+    }
+
+    /// Some unusual devices (such as certain bridge chips) require a reset and re-addressing before
+    /// every data byte. When enabled, `read_bytes` and `write_bytes` will reset the bus and address
+    /// the device last selected with `match_address` before each byte, except the first byte
+    /// following the address or command.
+    /// This is disabled by default, and should only be enabled for devices that need it.
+    pub fn set_reset_per_byte(&mut self, enabled: bool) {
+        self.reset_per_byte = enabled;
+    }
+
+    /// Disables the crc check of device addresses found by a search.
+    ///
+    /// **WARNING**: This weakens error detection. Any bit error while reading an address will go
+    /// unnoticed, and a corrupted address will be returned as if it were valid.
+    /// It exists only for non-conforming (counterfeit / clone) devices whose ROM code has an
+    /// incorrect crc, and should be left disabled (the default) for everything else.
+    pub fn set_skip_crc_check(&mut self, skip: bool) {
+        self.skip_crc_check = skip;
+    }
+
+    fn pin_error(&self, error: E) -> OneWireError<E> {
+        OneWireError::PinError {
+            error,
+            phase: self.phase,
+        }
+    }
+
     /// Disconnects the bus, letting another device (or the pull-up resistor) set the bus value
     pub fn release_bus(&mut self) -> OneWireResult<(), E> {
-        self.pin
-            .set_high()
-            .map_err(|err| OneWireError::PinError(err))
+        self.pin.set_high().map_err(|error| self.pin_error(error))
     }
 
     /// Drives the bus low
     pub fn set_bus_low(&mut self) -> OneWireResult<(), E> {
-        self.pin
-            .set_low()
-            .map_err(|err| OneWireError::PinError(err))
+        self.pin.set_low().map_err(|error| self.pin_error(error))
     }
 
     pub fn is_bus_high(&self) -> OneWireResult<bool, E> {
-        self.pin
-            .is_high()
-            .map_err(|err| OneWireError::PinError(err))
+        self.pin.is_high().map_err(|error| self.pin_error(error))
     }
 
     pub fn is_bus_low(&self) -> OneWireResult<bool, E> {
-        self.pin.is_low().map_err(|err| OneWireError::PinError(err))
+        self.pin.is_low().map_err(|error| self.pin_error(error))
     }
 
-    fn wait_for_high(&self, delay: &mut impl DelayUs<u16>) -> OneWireResult<(), E> {
-        // wait up to 250 µs for the bus to become high (from the pull-up resistor)
-        for _ in 0..125 {
+    /// Reads the current level of the bus.
+    /// A floating bus (such as a missing pull-up resistor) can't be detected from a single read,
+    /// and will be reported as whatever level the pin happens to read
+    pub fn bus_level(&self) -> OneWireResult<BusLevel, E> {
+        if self.is_bus_high()? {
+            Ok(BusLevel::High)
+        } else {
+            Ok(BusLevel::Low)
+        }
+    }
+
+    /// Waits up to `timeout_us` for the bus to be released (high), such as when another master
+    /// may be briefly holding the bus. This should be used before beginning a transaction.
+    /// Returns `BusBusy` if the bus is still low after the timeout
+    pub fn wait_for_idle(
+        &self,
+        timeout_us: u32,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E> {
+        let mut elapsed = 0;
+        loop {
             if self.is_bus_high()? {
                 return Ok(());
             }
-            delay.delay_us(2);
+            if elapsed >= timeout_us {
+                return Err(OneWireError::BusBusy);
+            }
+            delay.delay_us(POLL_INTERVAL_US);
+            elapsed += POLL_INTERVAL_US as u32;
+        }
+    }
+
+    fn wait_for_high(&self, delay: &mut impl DelayUs<u16>) -> OneWireResult<(), E> {
+        // wait for the bus to become high (from the pull-up resistor). The bus is always sampled
+        // at least once, even with a timeout shorter than the poll interval
+        match self.wait_for_idle(u32::from(self.recovery_timeout_us), delay) {
+            Err(OneWireError::BusBusy) => Err(OneWireError::Timeout),
+            result => result,
+        }
+    }
+
+    /// Waits for the bus to rise, when nothing should be holding it low.
+    /// If it doesn't rise within the recovery timeout, it is given up to `SHORT_CIRCUIT_US` longer:
+    /// returns `Timeout` if it rises in that time (the bus is just slow), or `BusShortCircuit` if not
+    fn check_not_shorted(&self, delay: &mut impl DelayUs<u16>) -> OneWireResult<(), E> {
+        match self.wait_for_high(delay) {
+            Err(OneWireError::Timeout) => match self.wait_for_idle(SHORT_CIRCUIT_US, delay) {
+                Ok(()) => Err(OneWireError::Timeout),
+                Err(OneWireError::BusBusy) => Err(OneWireError::BusShortCircuit),
+                Err(err) => Err(err),
+            },
+            result => result,
+        }
+    }
+
+    /// Pulls the bus low briefly, then measures how long (in whole microseconds) the bus takes to
+    /// rise after being released. Devices see this as a 1 bit, so it should only be used between
+    /// transactions, while the devices are waiting for a reset.
+    /// Returns `Timeout` if the bus doesn't rise within the recovery timeout
+    /// (see `set_recovery_timeout`)
+    pub fn measure_rise_time(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<u16, E> {
+        let timing = *self.timing();
+        self.wait_for_high(delay)?;
+        self.set_bus_low()?;
+        delay.delay_us(timing.write_1_low_us);
+        self.release_bus()?;
+        let mut rise_us = 0;
+        while self.is_bus_low()? {
+            if rise_us >= self.recovery_timeout_us {
+                return Err(OneWireError::Timeout);
+            }
+            delay.delay_us(1);
+            rise_us += 1;
         }
-        Err(OneWireError::BusNotHigh)
+        delay.delay_us(timing.write_1_recovery_us.saturating_sub(rise_us));
+        Ok(rise_us)
+    }
+
+    /// Measures the rise time of the bus (see `measure_rise_time`), and returns true if it is within
+    /// `max_rise_time_us` of the timing for `speed`. This should be checked before switching to
+    /// overdrive, which needs a much faster rise time than standard speed
+    pub fn check_rise_time(
+        &mut self,
+        speed: Speed,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<bool, E> {
+        let max_rise_time_us = match speed {
+            Speed::Standard => self.timing.max_rise_time_us,
+            Speed::Overdrive => self.overdrive_timing.max_rise_time_us,
+        };
+        Ok(self.measure_rise_time(delay)? <= max_rise_time_us)
     }
 
     /// Sends a reset pulse, then returns true if a device is present
+    ///
+    /// The bus is sampled repeatedly across the presence detect window configured in the `Timing`,
+    /// and a device is considered present if the bus was low at any point in that window.
+    ///
+    /// The reset uses the timing for the current speed. At overdrive speed, the short reset pulse
+    /// keeps overdrive devices at overdrive speed, so several transactions can be made without
+    /// selecting the device again with an overdrive ROM command. Use `exit_overdrive` to reset all
+    /// devices back to standard speed.
+    ///
+    /// Returns `Timeout` if the bus rises too slowly before the reset pulse is sent (see
+    /// `set_recovery_timeout`), or `BusShortCircuit` if it doesn't rise at all. Returns
+    /// `BusNotHigh` if a presence pulse is still holding the bus low at the end of the reset
+    /// recovery time.
     pub fn reset(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<bool, E> {
-        self.wait_for_high(delay)?;
+        Ok(self.reset_measuring_presence(false, delay)?.0)
+    }
 
-        self.set_bus_low()?;
-        delay.delay_us(480); // Maxim recommended wait time
+    /// Sends a reset pulse, then returns true if a device is present, along with how long (in
+    /// microseconds) the bus was held low by the presence pulse.
+    /// More devices tend to hold the bus low for longer, but the width depends on the devices, so
+    /// this is only a rough estimate of how many devices are on the bus
+    pub fn reset_estimate_population(
+        &mut self,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(bool, u32), E> {
+        self.reset_measuring_presence(false, delay)
+    }
+
+    /// Sends a reset pulse, and measures how long the presence pulse holds the bus low.
+    /// The presence pulse is measured until the end of the reset recovery time
+    /// If `check_contention` is set, returns `BusContention` instead of waiting for the bus to be
+    /// released, and checks that the bus stays low during the reset pulse
+    fn reset_measuring_presence(
+        &mut self,
+        check_contention: bool,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(bool, u32), E> {
+        self.phase = Phase::Reset;
+        // every device sees the reset, so it uses the bus timing
+        self.active_family_timing = None;
+        let timing = *self.timing();
+
+        if check_contention {
+            if !self.is_bus_high()? {
+                return Err(OneWireError::BusContention);
+            }
+            self.set_bus_low()?;
+            let mut elapsed = 0;
+            while elapsed < timing.reset_low_us {
+                delay.delay_us(POLL_INTERVAL_US);
+                elapsed += POLL_INTERVAL_US;
+                if self.is_bus_high()? {
+                    // something else is driving the bus high
+                    self.release_bus()?;
+                    return Err(OneWireError::BusContention);
+                }
+            }
+        } else {
+            self.check_not_shorted(delay)?;
+            self.set_bus_low()?;
+            delay.delay_us(timing.reset_low_us);
+        }
 
         self.release_bus()?;
-        delay.delay_us(70); // Maxim recommended wait time
+        let mut elapsed = timing.presence_window_start_us;
+        delay.delay_us(elapsed);
+
+        let mut device_present = false;
+        loop {
+            if self.is_bus_low()? {
+                device_present = true;
+                break;
+            }
+            if elapsed >= timing.presence_window_end_us {
+                break;
+            }
+            delay.delay_us(POLL_INTERVAL_US);
+            elapsed += POLL_INTERVAL_US;
+        }
+
+        let mut presence_low_us = 0;
+        if device_present {
+            while elapsed < timing.reset_recovery_us && self.is_bus_low()? {
+                delay.delay_us(POLL_INTERVAL_US);
+                elapsed += POLL_INTERVAL_US;
+                presence_low_us += u32::from(POLL_INTERVAL_US);
+            }
+            // a presence pulse must end within the reset recovery time
+            if self.is_bus_low()? {
+                return Err(OneWireError::BusNotHigh);
+            }
+        }
+
+        delay.delay_us(timing.reset_recovery_us.saturating_sub(elapsed));
+        Ok((device_present, presence_low_us))
+    }
+
+    /// Sends a reset pulse (see `reset`) on a bus that may be shared with another master.
+    /// Returns `BusContention` if the bus isn't idle (high) when the reset starts, or if the bus
+    /// is driven high by something else during the reset pulse
+    pub fn reset_collision_aware(
+        &mut self,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<bool, E> {
+        Ok(self.reset_measuring_presence(true, delay)?.0)
+    }
+
+    /// Sends a reset pulse at the current speed, then returns true if a device is present, along
+    /// with the speed the bus is now using.
+    /// At overdrive speed, if no device responds, the devices may have dropped back to standard
+    /// speed, so a standard speed reset is tried and the bus is switched back to standard speed
+    pub fn reset_with_fallback(
+        &mut self,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(bool, Speed), E> {
+        if self.speed == Speed::Overdrive {
+            if self.reset(delay)? {
+                return Ok((true, Speed::Overdrive));
+            }
+            self.speed = Speed::Standard;
+        }
+        Ok((self.reset(delay)?, self.speed))
+    }
 
-        let device_present = self.is_bus_low()?;
+    /// Returns all devices to standard speed with a standard speed reset, and switches the bus back
+    /// to standard speed. Returns true if a device is present.
+    ///
+    /// A reset is seen by every device, so this can't return a single device to standard speed:
+    /// after a standard speed reset, all devices on the bus are at standard speed
+    pub fn exit_overdrive(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<bool, E> {
+        self.speed = Speed::Standard;
+        self.reset(delay)
+    }
 
-        delay.delay_us(410); // Maxim recommended wait time
-        Ok(device_present)
+    /// Tries to return the bus and all devices to a known state after an error, such as a device
+    /// left part way through a transaction by a brownout.
+    /// Several standard speed reset pulses are sent, each twice the normal length and followed by
+    /// twice the normal recovery time, so every device sees a reset whatever it was doing. A
+    /// normal standard speed reset then checks that the bus rises, returning `BusShortCircuit` if
+    /// it is still held low. Afterwards, all devices (and the bus) are at standard speed
+    pub fn recover(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<(), E> {
+        self.phase = Phase::Reset;
+        self.speed = Speed::Standard;
+        self.active_family_timing = None;
+        let timing = *self.timing();
+        for _ in 0..RECOVERY_RESETS {
+            self.set_bus_low()?;
+            delay_us_long(delay, 2 * u32::from(timing.reset_low_us));
+            self.release_bus()?;
+            delay_us_long(delay, 2 * u32::from(timing.reset_recovery_us));
+        }
+        self.exit_overdrive(delay)?;
+        Ok(())
     }
 
     pub fn read_bit(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<bool, E> {
+        let timing = *self.timing();
         self.set_bus_low()?;
-        delay.delay_us(6); // Maxim recommended wait time
+        delay.delay_us(timing.read_low_us);
 
         self.release_bus()?;
-        delay.delay_us(9); // Maxim recommended wait time
+        delay.delay_us(timing.read_sample_us);
 
         let bit_value = self.is_bus_high()?;
-        delay.delay_us(55); // Maxim recommended wait time
+        delay.delay_us(timing.read_recovery_us);
+        if !bit_value && self.is_bus_low()? {
+            // a device sending a 0 has released the bus by the end of the slot
+            self.check_not_shorted(delay)?;
+        }
         Ok(bit_value)
     }
 
     pub fn read_byte(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<u8, E> {
+        self.read_byte_in(Phase::DataRead, delay)
+    }
+
+    /// Reads a byte as part of the given phase of a transaction
+    pub(crate) fn read_byte_in(
+        &mut self,
+        phase: Phase,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<u8, E> {
+        self.phase = phase;
         let mut output: u8 = 0;
         for _ in 0..8 {
             output >>= 1;
@@ -121,32 +618,66 @@ where
         }
         Ok(output)
     }
+    /// Reads a byte, and adds it to `crc`. This allows the crc of a response to be checked as it
+    /// is read, when the length isn't known in advance or the response isn't buffered
+    pub fn read_byte_crc(
+        &mut self,
+        crc: &mut crc::Crc8,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<u8, E> {
+        let byte = self.read_byte(delay)?;
+        crc.update(byte);
+        Ok(byte)
+    }
+
     pub fn read_bytes(
         &mut self,
         output: &mut [u8],
         delay: &mut impl DelayUs<u16>,
     ) -> OneWireResult<(), E> {
-        for i in 0..output.len() {
-            output[i] = self.read_byte(delay)?;
+        for byte in output.iter_mut() {
+            self.reselect_for_byte(delay)?;
+            *byte = self.read_byte(delay)?;
         }
         Ok(())
     }
 
+    /// Reads bytes into `output` until `terminator` is read, for responses that end with a known
+    /// byte instead of having a fixed length. The terminator is stored in `output`, and included
+    /// in the returned number of bytes read.
+    /// Returns `BufferTooSmall` if `output` is filled without reading the terminator
+    pub fn read_until(
+        &mut self,
+        terminator: u8,
+        output: &mut [u8],
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<usize, E> {
+        for (index, byte) in output.iter_mut().enumerate() {
+            *byte = self.read_byte(delay)?;
+            if *byte == terminator {
+                return Ok(index + 1);
+            }
+        }
+        Err(OneWireError::BufferTooSmall)
+    }
+
     pub fn write_1_bit(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<(), E> {
+        let timing = *self.timing();
         self.set_bus_low()?;
-        delay.delay_us(6); // Maxim recommended wait time
+        delay.delay_us(timing.write_1_low_us);
 
         self.release_bus()?;
-        delay.delay_us(64); // Maxim recommended wait time
+        delay.delay_us(timing.write_1_recovery_us);
         Ok(())
     }
 
     pub fn write_0_bit(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<(), E> {
+        let timing = *self.timing();
         self.set_bus_low()?;
-        delay.delay_us(60); // Maxim recommended wait time
+        delay.delay_us(timing.write_0_low_us);
 
         self.release_bus()?;
-        delay.delay_us(10); // Maxim recommended wait time
+        delay.delay_us(timing.write_0_recovery_us);
         Ok(())
     }
 
@@ -162,11 +693,18 @@ where
         }
     }
 
-    pub fn write_byte(
+    pub fn write_byte(&mut self, value: u8, delay: &mut impl DelayUs<u16>) -> OneWireResult<(), E> {
+        self.write_byte_in(Phase::DataWrite, value, delay)
+    }
+
+    /// Writes a byte as part of the given phase of a transaction
+    pub(crate) fn write_byte_in(
         &mut self,
+        phase: Phase,
         mut value: u8,
         delay: &mut impl DelayUs<u16>,
     ) -> OneWireResult<(), E> {
+        self.phase = phase;
         for _ in 0..8 {
             self.write_bit(value & 0x01 == 0x01, delay)?;
             value >>= 1;
@@ -174,17 +712,67 @@ where
         Ok(())
     }
 
+    /// Writes a byte, then enables a strong pull-up for `hold_us` to power parasite powered devices
+    /// through a command like CONVERT_T or COPY_SCRATCHPAD.
+    /// The strong pull-up is enabled as soon as the last bit releases the bus, rather than after the
+    /// slot's recovery time, so it is always within the 10µs that devices allow.
+    /// `strong_pullup` is called the same way as in `with_strong_pullup`
+    pub fn write_byte_with_power(
+        &mut self,
+        mut value: u8,
+        hold_us: u32,
+        mut strong_pullup: impl FnMut(bool),
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E> {
+        self.phase = Phase::DataWrite;
+        for _ in 0..7 {
+            self.write_bit(value & 0x01 == 0x01, delay)?;
+            value >>= 1;
+        }
+        let timing = *self.timing();
+        self.set_bus_low()?;
+        if value & 0x01 == 0x01 {
+            delay.delay_us(timing.write_1_low_us);
+        } else {
+            delay.delay_us(timing.write_0_low_us);
+        }
+        self.release_bus()?;
+        strong_pullup(true);
+        delay_us_long(delay, hold_us);
+        strong_pullup(false);
+        self.release_bus()
+    }
+
     pub fn write_bytes(
         &mut self,
         bytes: &[u8],
         delay: &mut impl DelayUs<u16>,
     ) -> OneWireResult<(), E> {
-        for i in 0..bytes.len() {
-            self.write_byte(bytes[i], delay)?;
+        for byte in bytes {
+            self.reselect_for_byte(delay)?;
+            self.write_byte(*byte, delay)?;
         }
         Ok(())
     }
 
+    /// Returns an iterator that reads `count` bytes from the bus, one byte per call to `next`.
+    /// This allows a large response to be processed as it arrives (or abandoned part way through)
+    /// without buffering it. The iterator ends after the first error
+    pub fn read_streaming<'a, 'b, D>(
+        &'a mut self,
+        count: usize,
+        delay: &'b mut D,
+    ) -> ReadStream<'a, 'b, T, D>
+    where
+        D: DelayUs<u16>,
+    {
+        ReadStream {
+            onewire: self,
+            delay,
+            remaining: count,
+        }
+    }
+
     /// Address a specific device. All others will wait for a reset pulse.
     /// This should only be called after a reset, and should be immediately followed by another command
     pub fn match_address(
@@ -192,15 +780,77 @@ where
         address: &Address,
         delay: &mut impl DelayUs<u16>,
     ) -> OneWireResult<(), E> {
-        self.write_byte(commands::MATCH_ROM, delay)?;
-        self.write_bytes(&address.0.to_le_bytes(), delay)?;
+        self.write_byte_in(Phase::Address, commands::MATCH_ROM, delay)?;
+        for byte in &address.to_le_bytes() {
+            self.write_byte_in(Phase::Address, *byte, delay)?;
+        }
+        self.select(*address);
+        Ok(())
+    }
+
+    /// Records the addressed device, and switches to its family timing at the current speed (if any)
+    fn select(&mut self, address: Address) {
+        self.selected = Some(address);
+        self.active_family_timing = self
+            .family_timing
+            .iter()
+            .flatten()
+            .find(|(family, speed, _)| *family == address.family_code() && *speed == self.speed)
+            .map(|(_, _, timing)| *timing);
+    }
+
+    /// Sends a reset, followed by RESUME, which addresses the device that was last selected (with
+    /// `match_address`, `read_rom` or `fast_select`) without sending its address again.
+    /// Returns `NoDeviceSelected` if no device has been selected, or the last ROM command was
+    /// `skip_address`.
+    /// Devices only remember being selected until another ROM command selects a different device,
+    /// so this must not be used after communicating with another device through a different `OneWire`
+    pub fn resume(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<(), E> {
+        let address = self.selected.ok_or(OneWireError::NoDeviceSelected)?;
+        self.reset(delay)?;
+        self.write_byte_in(Phase::Address, commands::RESUME, delay)?;
+        self.select(address);
         Ok(())
     }
 
+    /// Sends a reset, RESUME (see `resume`), and then the supplied command
+    /// This should be followed by any reading/writing, if needed by the command used
+    pub fn send_command_resume(
+        &mut self,
+        command: u8,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E> {
+        self.resume(delay)?;
+        self.write_byte_in(Phase::Command, command, delay)
+    }
+
     /// Address all devices on the bus simultaneously.
     /// This should only be called after a reset, and should be immediately followed by another command
     pub fn skip_address(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<(), E> {
-        self.write_byte(commands::SKIP_ROM, delay)?;
+        self.write_byte_in(Phase::Address, commands::SKIP_ROM, delay)?;
+        self.selected = None;
+        Ok(())
+    }
+
+    /// Releases the bus at the end of a transaction, and leaves it idle for `inter_transaction_us`
+    fn end_transaction(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<(), E> {
+        self.release_bus()?;
+        let inter_transaction_us = self.timing().inter_transaction_us;
+        if inter_transaction_us > 0 {
+            delay.delay_us(inter_transaction_us);
+        }
+        Ok(())
+    }
+
+    /// Resets the bus and addresses the selected device again, if `reset_per_byte` is enabled.
+    /// The first byte straight after addressing or a command is sent as is, since a reset there
+    /// would make the device drop the command
+    fn reselect_for_byte(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<(), E> {
+        if self.reset_per_byte && !matches!(self.phase, Phase::Address | Phase::Command) {
+            let address = self.selected.ok_or(OneWireError::NoDeviceSelected)?;
+            self.reset(delay)?;
+            self.match_address(&address, delay)?;
+        }
         Ok(())
     }
 
@@ -218,107 +868,708 @@ where
         } else {
             self.skip_address(delay)?;
         }
-        self.write_byte(command, delay)?;
+        self.write_byte_in(Phase::Command, command, delay)?;
         Ok(())
     }
 
-    /// Returns an iterator that iterates over all device addresses on the bus
-    /// They can be filtered to only alarming devices if needed
-    /// There is no requirement to immediately finish iterating all devices, but if devices are
-    /// added / removed / change alarm state, the search may return an error or fail to find a device
-    /// Device addresses will always be returned in the same order (lowest to highest, Little Endian)
-    pub fn devices<'a, 'b, D>(
-        &'a mut self,
-        only_alarming: bool,
-        delay: &'b mut D,
-    ) -> DeviceSearch<'a, 'b, T, D>
-    where
-        D: DelayUs<u16>,
-    {
-        DeviceSearch {
-            onewire: self,
-            delay,
-            state: None,
-            finished: false,
-            only_alarming,
-        }
+    /// Copies the scratchpad of a device to EEPROM with COPY_SCRATCHPAD (0x48), and waits for the
+    /// copy to finish. Parasite powered devices need `copy_scratchpad_with_power` instead
+    pub fn copy_scratchpad(
+        &mut self,
+        address: &Address,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E> {
+        self.send_command(commands::COPY_SCRATCHPAD, Some(address), delay)?;
+        delay_us_long(delay, COPY_SCRATCHPAD_US);
+        self.end_transaction(delay)
     }
 
-    /// Search for device addresses on the bus
-    /// They can be filtered to only alarming devices if needed
-    /// Start the first search with a search_state of `None`, then use the returned state for subsequent searches
-    /// There is no time limit for continuing a search, but if devices are
-    /// added / removed / change alarm state, the search may return an error or fail to find a device
-    /// Device addresses will always be returned in the same order (lowest to highest, Little Endian)
-    pub fn device_search(
+    /// Same as `copy_scratchpad`, but powers a parasite powered device with a strong pull-up while
+    /// it copies (see `write_byte_with_power`)
+    pub fn copy_scratchpad_with_power(
         &mut self,
-        search_state: Option<&SearchState>,
-        only_alarming: bool,
+        address: &Address,
+        strong_pullup: impl FnMut(bool),
         delay: &mut impl DelayUs<u16>,
-    ) -> OneWireResult<Option<(Address, SearchState)>, E> {
-        if let Some(search_state) = search_state {
-            if search_state.discrepancies == 0 {
-                return Ok(None);
-            }
-        }
-
-        if !self.reset(delay)? {
-            return Ok(None);
-        }
-        if only_alarming {
-            self.write_byte(commands::SEARCH_ALARM, delay)?;
-        } else {
-            self.write_byte(commands::SEARCH_NORMAL, delay)?;
-        }
+    ) -> OneWireResult<(), E> {
+        self.reset(delay)?;
+        self.match_address(address, delay)?;
+        self.write_byte_with_power(
+            commands::COPY_SCRATCHPAD,
+            COPY_SCRATCHPAD_US,
+            strong_pullup,
+            delay,
+        )?;
+        self.end_transaction(delay)
+    }
 
-        let mut last_discrepancy_index: u8 = 0;
-        let mut address;
-        let mut discrepancies;
-        let continue_start_bit;
+    /// Reloads the scratchpad of a device from EEPROM with RECALL_E2 (0xB8)
+    pub fn recall_e2(
+        &mut self,
+        address: &Address,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E> {
+        self.send_command(commands::RECALL_E2, Some(address), delay)?;
+        self.end_transaction(delay)
+    }
 
-        if let Some(search_state) = search_state {
-            // follow up to the last discrepancy
-            for bit_index in 0..search_state.last_discrepancy_index {
-                let _false_bit = !self.read_bit(delay)?;
-                let _true_bit = !self.read_bit(delay)?;
-                let was_discrepancy_bit =
-                    (search_state.discrepancies & (1_u64 << (bit_index as u64))) != 0;
-                if was_discrepancy_bit {
-                    last_discrepancy_index = bit_index;
+    /// Sends READ_POWER_SUPPLY to a device (or all devices if `address` is `None`), and returns
+    /// false if any of them are parasite powered (they hold the bus low for the read slot).
+    /// Parasite powered devices need a strong pull-up while converting or copying to EEPROM
+    pub fn read_power_supply(
+        &mut self,
+        address: Option<&Address>,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<bool, E> {
+        self.send_command(commands::READ_POWER_SUPPLY, address, delay)?;
+        self.phase = Phase::DataRead;
+        let externally_powered = self.read_bit(delay)?;
+        self.end_transaction(delay)?;
+        Ok(externally_powered)
+    }
+
+    /// Sends `command` (see `send_command`), then waits `post_command_us` before returning, for
+    /// devices that need time to process a command before data can be read or written
+    pub fn send_command_delayed(
+        &mut self,
+        command: u8,
+        address: Option<&Address>,
+        post_command_us: u32,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E> {
+        self.send_command(command, address, delay)?;
+        delay_us_long(delay, post_command_us);
+        Ok(())
+    }
+
+    /// Sends `command` (see `send_command`), then reads a single status byte and converts it into
+    /// `F`. This allows drivers to read a status byte straight into their own flags type, such as a
+    /// `bitflags` struct implementing `From<u8>`
+    pub fn read_status<F: From<u8>>(
+        &mut self,
+        address: Option<&Address>,
+        command: u8,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<F, E> {
+        self.send_command(command, address, delay)?;
+        let status = self.read_byte(delay)?;
+        self.end_transaction(delay)?;
+        Ok(F::from(status))
+    }
+
+    /// Reads the 9 byte scratchpad of a DS18B20 (or a device with the same scratchpad layout) with
+    /// READ_SCRATCHPAD, and checks the crc in the last byte, returning `CrcMismatch` if it fails
+    pub fn read_scratchpad(
+        &mut self,
+        address: &Address,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<[u8; 9], E> {
+        self.read_scratchpad_crc8(address, commands::READ_SCRATCHPAD, delay)
+    }
+
+    /// Sends `command` to a device, and reads `N` bytes where the last byte is the crc8 of the
+    /// bytes before it, as most devices send their scratchpad (or other registers).
+    /// Returns `CrcMismatch` if the crc check fails
+    pub fn read_scratchpad_crc8<const N: usize>(
+        &mut self,
+        address: &Address,
+        command: u8,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<[u8; N], E> {
+        self.send_command(command, Some(address), delay)?;
+        let mut scratchpad = [0; N];
+        for byte in scratchpad.iter_mut() {
+            *byte = self.read_byte(delay)?;
+        }
+        self.end_transaction(delay)?;
+        crc::check_crc8(&scratchpad)?;
+        Ok(scratchpad)
+    }
+
+    /// Sends `command` to each device in `addresses`, and reads `bytes_each` bytes from each
+    /// into consecutive chunks of `output`.
+    /// When the same device is read more than once in a row, it is re-addressed with a RESUME
+    /// command instead of MATCH_ROM, avoiding sending the full 64-bit address again
+    pub fn read_many(
+        &mut self,
+        addresses: &[Address],
+        command: u8,
+        bytes_each: usize,
+        output: &mut [u8],
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E> {
+        if output.len() < addresses.len() * bytes_each {
+            return Err(OneWireError::BufferTooSmall);
+        }
+        let mut previous = None;
+        for (i, address) in addresses.iter().enumerate() {
+            if previous == Some(address) {
+                self.resume(delay)?;
+            } else {
+                self.reset(delay)?;
+                self.match_address(address, delay)?;
+            }
+            self.write_byte_in(Phase::Command, command, delay)?;
+            self.read_bytes(&mut output[i * bytes_each..(i + 1) * bytes_each], delay)?;
+            self.end_transaction(delay)?;
+            previous = Some(address);
+        }
+        Ok(())
+    }
+
+    /// Starts a temperature conversion on all devices at overdrive speed, then holds the bus high
+    /// with a strong pull-up for `hold_us` while the devices convert (as needed by parasite powered
+    /// devices).
+    /// If the bus is at standard speed, all devices are switched to overdrive with OVERDRIVE_SKIP_ROM
+    /// and the bus stays at overdrive speed afterwards.
+    /// `strong_pullup` is called with `true` to enable the strong pull-up, and `false` to disable it
+    pub fn overdrive_convert_all(
+        &mut self,
+        hold_us: u32,
+        strong_pullup: impl FnMut(bool),
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E> {
+        self.reset(delay)?;
+        if self.speed == Speed::Standard {
+            self.write_byte_in(Phase::Address, commands::OVERDRIVE_SKIP_ROM, delay)?;
+            self.selected = None;
+            self.speed = Speed::Overdrive;
+        } else {
+            self.skip_address(delay)?;
+        }
+        self.write_byte_with_power(commands::CONVERT_T, hold_us, strong_pullup, delay)?;
+        self.end_transaction(delay)
+    }
+
+    /// Enables a strong pull-up for `duration_us`, then disables it and releases the bus.
+    /// Parasite powered devices draw more current than the pull-up resistor can supply while
+    /// converting or copying to EEPROM, so the bus must be actively held high, starting within
+    /// 10µs of the end of the command (a DS18B20 needs 750ms for a 12-bit conversion).
+    /// `strong_pullup` is called with `true` to enable the strong pull-up (such as a GPIO driving
+    /// a transistor to the supply), and `false` to disable it
+    pub fn with_strong_pullup(
+        &mut self,
+        duration_us: u32,
+        mut strong_pullup: impl FnMut(bool),
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E> {
+        strong_pullup(true);
+        delay_us_long(delay, duration_us);
+        strong_pullup(false);
+        self.release_bus()
+    }
+
+    /// Selects a single device and switches it (and the bus) to overdrive speed with
+    /// OVERDRIVE_MATCH_ROM. The returned `Selected` addresses the device with RESUME for each
+    /// command, which is the fastest way to repeatedly communicate with a single device.
+    /// The device must support overdrive speed
+    pub fn fast_select(
+        &mut self,
+        address: &Address,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<Selected<'_, T>, E> {
+        self.reset(delay)?;
+        if self.speed == Speed::Standard {
+            self.write_byte_in(Phase::Address, commands::OVERDRIVE_MATCH_ROM, delay)?;
+            // the address is sent at overdrive speed
+            self.speed = Speed::Overdrive;
+            for byte in &address.to_le_bytes() {
+                self.write_byte_in(Phase::Address, *byte, delay)?;
+            }
+            self.select(*address);
+        } else {
+            self.match_address(address, delay)?;
+        }
+        Ok(Selected::new(self, *address))
+    }
+
+    /// Returns an iterator that iterates over all device addresses on the bus
+    /// They can be filtered to only alarming devices if needed
+    /// There is no requirement to immediately finish iterating all devices, but if devices are
+    /// added / removed / change alarm state, the search may return an error or fail to find a device
+    /// Device addresses will always be returned in the same order (lowest to highest, Little Endian)
+    /// The search only sends ROM commands, and never a function command, so it doesn't change the
+    /// state of any device (such as its alarm state)
+    pub fn devices<'a, 'b, D>(
+        &'a mut self,
+        only_alarming: bool,
+        delay: &'b mut D,
+    ) -> DeviceSearch<'a, 'b, T, D>
+    where
+        D: DelayUs<u16>,
+    {
+        DeviceSearch {
+            onewire: self,
+            delay,
+            state: None,
+            finished: false,
+            only_alarming,
+            skip_errors: false,
+        }
+    }
+
+    /// Returns an iterator over the addresses of devices with the given family code (see `devices`).
+    /// The family code is sent first, so the search follows it for the first 8 bits, and never
+    /// visits devices of other families.
+    /// The same search can be made one device at a time with `device_search`, starting from
+    /// `SearchState::seed_from_address(&Address(family as u64), 8)`
+    pub fn devices_with_family<'a, 'b, D>(
+        &'a mut self,
+        family: u8,
+        only_alarming: bool,
+        delay: &'b mut D,
+    ) -> DeviceSearch<'a, 'b, T, D>
+    where
+        D: DelayUs<u16>,
+    {
+        DeviceSearch {
+            onewire: self,
+            delay,
+            state: Some(SearchState::seed_from_address(
+                &Address(u64::from(family)),
+                8,
+            )),
+            finished: false,
+            only_alarming,
+            skip_errors: false,
+        }
+    }
+
+    /// Search for device addresses on the bus
+    /// They can be filtered to only alarming devices if needed
+    /// Start the first search with a search_state of `None`, then use the returned state for subsequent searches
+    /// There is no time limit for continuing a search, but if devices are
+    /// added / removed / change alarm state, the search may return an error or fail to find a device
+    /// Device addresses will always be returned in the same order (lowest to highest, Little Endian)
+    pub fn device_search(
+        &mut self,
+        search_state: Option<&SearchState>,
+        only_alarming: bool,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<Option<(Address, SearchState)>, E> {
+        match self.search_pass(search_state, only_alarming, delay)? {
+            Some(pass) => {
+                self.check_address_crc(pass.address)?;
+                Ok(Some((Address(pass.address), pass)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// A single pass of `device_search`, without checking the crc of the address found
+    fn search_pass(
+        &mut self,
+        search_state: Option<&SearchState>,
+        only_alarming: bool,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<Option<SearchState>, E> {
+        if !self.start_search(search_state, only_alarming, delay)? {
+            return Ok(None);
+        }
+        let mut pass = SearchState::next_pass(search_state);
+        for bit_index in 0..64 {
+            if !self.search_bit(bit_index, search_state, &mut pass, delay)? {
+                return Ok(None);
+            }
+        }
+        self.end_transaction(delay)?;
+        Ok(Some(pass))
+    }
+
+    /// Returns the address of the only device on the bus.
+    /// This is intended for buses that should have exactly one device attached, and returns
+    /// `NoDevicePresent` or `MultipleDevices` if that isn't the case.
+    /// The address is found with a single search pass, so its crc is validated
+    pub fn expect_single(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<Address, E> {
+        match self.device_search(None, false, delay)? {
+            None => Err(OneWireError::NoDevicePresent),
+            Some((_, search_state)) if search_state.discrepancies != 0 => {
+                Err(OneWireError::MultipleDevices)
+            }
+            Some((address, _)) => Ok(address),
+        }
+    }
+
+    /// Returns an iterator over the addresses of all devices on the bus (see `devices`), which is
+    /// guaranteed to not change the state of any device: only ROM commands are sent, and never a
+    /// function command. This can list the devices on a bus without disturbing their alarm state,
+    /// but can't read any device data
+    pub fn scan_passive<'a, 'b, D>(&'a mut self, delay: &'b mut D) -> DeviceSearch<'a, 'b, T, D>
+    where
+        D: DelayUs<u16>,
+    {
+        self.devices(false, delay)
+    }
+
+    /// The longest time (in microseconds) a full search of a bus with `expected_devices` devices
+    /// can take with the current speed and timing, ignoring time spent waiting for the bus to be
+    /// released. Each device takes a search pass of a reset, the search command, and 64 search
+    /// triplets. This can be used to check a search fits in a time slot before starting it
+    pub fn max_search_duration_us(&self, expected_devices: usize) -> u32 {
+        let timing = self.timing();
+        let reset_us = u32::from(timing.reset_low_us) + u32::from(timing.reset_recovery_us);
+        let read_slot_us = u32::from(timing.read_low_us)
+            + u32::from(timing.read_sample_us)
+            + u32::from(timing.read_recovery_us);
+        let write_slot_us = core::cmp::max(
+            u32::from(timing.write_0_low_us) + u32::from(timing.write_0_recovery_us),
+            u32::from(timing.write_1_low_us) + u32::from(timing.write_1_recovery_us),
+        );
+        let triplet_us = 2 * read_slot_us + write_slot_us + u32::from(timing.inter_triplet_us);
+        let pass_us =
+            reset_us + 8 * write_slot_us + 64 * triplet_us + u32::from(timing.inter_transaction_us);
+        pass_us.saturating_mul(expected_devices as u32)
+    }
+
+    /// Finds all devices on the bus, and writes their addresses to `output`, returning the number
+    /// of devices found.
+    /// If the search fails with an error that a noisy bus can cause (`UnexpectedResponse` or
+    /// `CrcMismatch`), the bus is recovered with `recover` and the search is restarted from the
+    /// beginning, up to `max_restarts` times. Any other error is returned immediately.
+    /// Returns `BufferTooSmall` if there are more devices than fit in `output`
+    pub fn search_all_robust(
+        &mut self,
+        output: &mut [Address],
+        max_restarts: u8,
+        only_alarming: bool,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<usize, E> {
+        let mut restarts = 0;
+        loop {
+            match self.search_into(output, only_alarming, delay) {
+                Err(OneWireError::UnexpectedResponse) | Err(OneWireError::CrcMismatch { .. })
+                    if restarts < max_restarts =>
+                {
+                    restarts += 1;
+                    self.recover(delay)?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Continues a search (see `device_search`) for up to `output.len()` more devices, writing their
+    /// addresses to `output`. Returns the number of devices found, along with the search state to
+    /// continue the search from. Fewer devices are returned once the search is finished.
+    /// An empty `output` returns immediately, with the unchanged search state
+    pub fn device_search_bounded(
+        &mut self,
+        search_state: Option<&SearchState>,
+        only_alarming: bool,
+        output: &mut [Address],
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(usize, Option<SearchState>), E> {
+        let mut search_state = search_state.cloned();
+        let mut count = 0;
+        for slot in output.iter_mut() {
+            match self.device_search(search_state.as_ref(), only_alarming, delay)? {
+                Some((address, state)) => {
+                    *slot = address;
+                    count += 1;
+                    search_state = Some(state);
+                }
+                None => break,
+            }
+        }
+        Ok((count, search_state))
+    }
+
+    /// Finds all devices on the bus, and returns how many there are.
+    /// The crc of every address is still checked, so noise on the bus is reported as an error
+    /// instead of a miscount
+    pub fn count_devices(
+        &mut self,
+        only_alarming: bool,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<usize, E> {
+        self.devices(only_alarming, delay)
+            .try_fold(0, |count, address| address.map(|_| count + 1))
+    }
+
+    /// Searches the bus `confirm_passes` times, and writes the addresses of the devices that were
+    /// found by every search to `output`, returning the number of devices. Devices with an
+    /// intermittent connection, which come and go between searches, are left out.
+    /// A device dropping out part way through a search makes the search fail with
+    /// `UnexpectedResponse` or `CrcMismatch`. The search then counts as one that only found the
+    /// devices before the failure, so the devices after it are left out as well.
+    /// All devices are searched for, not only alarming ones.
+    /// Returns `BufferTooSmall` if the first search finds more devices than fit in `output`
+    pub fn search_stable(
+        &mut self,
+        output: &mut [Address],
+        confirm_passes: u8,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<usize, E> {
+        let mut count = 0;
+        for pass in 0..confirm_passes.max(1) {
+            // both lists are in search order, so devices that weren't found again can be removed
+            // as the search passes them
+            let mut index = 0;
+            let mut kept = 0;
+            for address in self.devices(false, delay) {
+                let address = match address {
+                    Ok(address) => address,
+                    Err(OneWireError::UnexpectedResponse)
+                    | Err(OneWireError::CrcMismatch { .. }) => break,
+                    Err(err) => return Err(err),
+                };
+                if pass == 0 {
+                    *output.get_mut(kept).ok_or(OneWireError::BufferTooSmall)? = address;
+                    kept += 1;
+                    continue;
+                }
+                while index < count && output[index] < address {
+                    index += 1;
+                }
+                if index < count && output[index] == address {
+                    output[kept] = address;
+                    kept += 1;
+                    index += 1;
                 }
-                let previous_chosen_bit =
-                    (search_state.address & (1_u64 << (bit_index as u64))) != 0;
+            }
+            count = kept;
+        }
+        Ok(count)
+    }
+
+    /// Finds all devices on the bus, and writes their addresses to `output`
+    fn search_into(
+        &mut self,
+        output: &mut [Address],
+        only_alarming: bool,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<usize, E> {
+        let mut count = 0;
+        for address in self.devices(only_alarming, delay) {
+            *output.get_mut(count).ok_or(OneWireError::BufferTooSmall)? = address?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Finds all devices on the bus, and checks whether each one supports overdrive speed by
+    /// selecting it with OVERDRIVE_MATCH_ROM and checking it responds to an overdrive reset.
+    /// Each address is written to `output` with the fastest speed the device supports, and the
+    /// number of devices found is returned. Returns `BufferTooSmall` if there are more devices
+    /// than fit in `output`.
+    /// All devices (and the bus) are returned to standard speed
+    pub fn classify_by_speed(
+        &mut self,
+        output: &mut [(Address, Speed)],
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<usize, E> {
+        if self.speed == Speed::Overdrive {
+            self.exit_overdrive(delay)?;
+        }
+
+        let mut count = 0;
+        for address in self.devices(false, delay) {
+            let slot = output.get_mut(count).ok_or(OneWireError::BufferTooSmall)?;
+            *slot = (address?, Speed::Standard);
+            count += 1;
+        }
+
+        for (address, speed) in output[..count].iter_mut() {
+            self.fast_select(address, delay)?;
+            if self.reset(delay)? {
+                *speed = Speed::Overdrive;
+            }
+            self.exit_overdrive(delay)?;
+        }
+        Ok(count)
+    }
+
+    /// Returns true if the device with `address` is on the bus, without searching for any other
+    /// devices.
+    /// MATCH_ROM isn't acknowledged by the device, so this uses a single search pass that follows
+    /// all 64 bits of `address`: the pass only completes if the device sends every bit of its
+    /// address. This takes about as long as finding one device with `device_search`, and returns
+    /// false if no device responds to the reset
+    pub fn is_present(
+        &mut self,
+        address: &Address,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<bool, E> {
+        let search_state = SearchState::seed_from_address(address, 64);
+        Ok(self
+            .device_search(Some(&search_state), false, delay)?
+            .is_some())
+    }
 
+    /// Searches the bus for a specific device, returning true as soon as it is found.
+    /// Since devices are always found in the same order, the search also stops (returning false)
+    /// as soon as a device that comes after `target` in that order is found
+    pub fn find_device(
+        &mut self,
+        target: &Address,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<bool, E> {
+        for address in self.devices(false, delay) {
+            let address = address?;
+            if address == *target {
+                return Ok(true);
+            }
+            if address > *target {
+                return Ok(false);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Runs a single search pass, and returns the index of the first (lowest) address bit where
+    /// the devices on the bus differ.
+    /// Returns `None` if there are no devices, or only a single device
+    pub fn first_discrepancy(
+        &mut self,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<Option<u8>, E> {
+        match self.device_search(None, false, delay)? {
+            Some((_, search_state)) if search_state.discrepancies != 0 => {
+                Ok(Some(search_state.discrepancies.trailing_zeros() as u8))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Reads the address of the only device on the bus with a READ_ROM command, which is faster
+    /// than a search. Returns `NoDevicePresent` if no device responds to the reset.
+    ///
+    /// This can only be used when there is a single device on the bus. If multiple devices respond,
+    /// their bits collide (a 0 from any device wins), and the crc check fails with `CrcMismatch`.
+    /// That is the expected error on a bus with more than one device, unless `skip_crc_check` is
+    /// enabled. Use `expect_single` to tell the two cases apart, at the cost of a search
+    pub fn read_rom(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<Address, E> {
+        if !self.reset(delay)? {
+            return Err(OneWireError::NoDevicePresent);
+        }
+        self.write_byte_in(Phase::Address, commands::READ_ROM, delay)?;
+        let mut rom = [0; 8];
+        for byte in rom.iter_mut() {
+            *byte = self.read_byte_in(Phase::Address, delay)?;
+        }
+        let address = u64::from_le_bytes(rom);
+        self.check_address_crc(address)?;
+        self.select(Address(address));
+        self.end_transaction(delay)?;
+        Ok(Address(address))
+    }
+
+    /// Reads the address of the only device on the bus (see `read_rom`), and checks that it has
+    /// the expected family code, returning `WrongFamily` if not
+    pub fn read_rom_expect(
+        &mut self,
+        family: u8,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<Address, E> {
+        let address = self.read_rom(delay)?;
+        address.expect_family(family)?;
+        Ok(address)
+    }
+
+    /// Reads the address of the only device on the bus (see `read_rom`), and returns it along with
+    /// a `Selected` that addresses the device with RESUME. This is the fastest way to repeatedly
+    /// communicate with the only device on a bus, without knowing its address in advance
+    pub fn read_rom_and_select(
+        &mut self,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(Address, Selected<'_, T>), E> {
+        let address = self.read_rom(delay)?;
+        Ok((address, Selected::new(self, address)))
+    }
+
+    /// Returns a search that is advanced manually with `IterativeSearch::step`, doing a bounded
+    /// amount of work per call
+    pub fn iterative_search(&mut self, only_alarming: bool) -> IterativeSearch<'_, T> {
+        IterativeSearch {
+            onewire: self,
+            previous: None,
+            pass: None,
+            only_alarming,
+            finished: false,
+        }
+    }
+
+    /// Checks the crc of a device address, unless `skip_crc_check` is enabled
+    fn check_address_crc(&self, address: u64) -> OneWireResult<(), E> {
+        if self.skip_crc_check {
+            return Ok(());
+        }
+        crc::check_crc8(&address.to_le_bytes())
+    }
+
+    /// Sends the reset and search command that begins a search pass.
+    /// Returns false if the search is already complete, or no devices are present
+    fn start_search(
+        &mut self,
+        search_state: Option<&SearchState>,
+        only_alarming: bool,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<bool, E> {
+        if let Some(search_state) = search_state {
+            if search_state.is_finished() {
+                return Ok(false);
+            }
+        }
+
+        if !self.reset(delay)? {
+            return Ok(false);
+        }
+        if only_alarming {
+            self.write_byte_in(Phase::Search, commands::SEARCH_ALARM, delay)?;
+        } else {
+            self.write_byte_in(Phase::Search, commands::SEARCH_NORMAL, delay)?;
+        }
+        Ok(true)
+    }
+
+    /// Performs a single search triplet (read the bit, read the complement, write the chosen bit),
+    /// recording the result in `pass`.
+    /// Returns false if no device matches the address prefix of a seeded search state
+    fn search_bit(
+        &mut self,
+        bit_index: u8,
+        previous: Option<&SearchState>,
+        pass: &mut SearchState,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<bool, E> {
+        let inter_triplet_us = self.timing().inter_triplet_us;
+        if bit_index > 0 && inter_triplet_us > 0 {
+            delay.delay_us(inter_triplet_us);
+        }
+        let false_bit = !self.read_bit(delay)?;
+        let true_bit = !self.read_bit(delay)?;
+        let address_mask = 1_u64 << (bit_index as u64);
+        let chosen_bit = match previous {
+            Some(previous) if bit_index < previous.last_discrepancy_index => {
+                // follow up to the last discrepancy
+                if (previous.discrepancies & address_mask) != 0 {
+                    pass.last_discrepancy_index = bit_index;
+                }
                 // choose the same as last time
-                self.write_bit(previous_chosen_bit, delay)?;
+                let previous_chosen_bit = (previous.address & address_mask) != 0;
+                let device_responded = if previous_chosen_bit {
+                    true_bit
+                } else {
+                    false_bit
+                };
+                if previous.is_seeded() && !device_responded {
+                    // No device has the requested address prefix
+                    return Ok(false);
+                }
+                previous_chosen_bit
             }
-            address = search_state.address;
-            // This is the discrepancy bit. False is always chosen to start, so choose true this time
+            Some(previous)
+                if bit_index == previous.last_discrepancy_index
+                    && (previous.discrepancies & address_mask) != 0 =>
             {
-                let false_bit = !self.read_bit(delay)?;
-                let true_bit = !self.read_bit(delay)?;
+                // This is the discrepancy bit. False is always chosen to start, so choose true this time
                 if !(false_bit && true_bit) {
                     // A different response was received than last search
                     return Err(OneWireError::UnexpectedResponse);
                 }
-                let address_mask = 1_u64 << (search_state.last_discrepancy_index as u64);
-                address |= address_mask;
-                self.write_bit(true, delay)?;
+                true
             }
-
-            //keep all discrepancies except the last one
-            discrepancies = search_state.discrepancies
-                & !(1_u64 << (search_state.last_discrepancy_index as u64));
-            continue_start_bit = search_state.last_discrepancy_index + 1;
-        } else {
-            address = 0;
-            discrepancies = 0;
-            continue_start_bit = 0;
-        }
-        for bit_index in continue_start_bit..64 {
-            let false_bit = !self.read_bit(delay)?;
-            let true_bit = !self.read_bit(delay)?;
-            let chosen_bit = match (false_bit, true_bit) {
+            _ => match (false_bit, true_bit) {
                 (false, false) => {
                     // No devices responded to the search request
                     return Err(OneWireError::UnexpectedResponse);
@@ -334,37 +1585,176 @@ where
                 (true, true) => {
                     // Discrepancy, multiple values reported
                     // choosing the lower value here
-                    discrepancies |= 1_u64 << (bit_index as u64);
-                    last_discrepancy_index = bit_index;
+                    pass.discrepancies |= address_mask;
+                    pass.last_discrepancy_index = bit_index;
                     false
                 }
-            };
-            let address_mask = 1_u64 << (bit_index as u64);
-            if chosen_bit {
-                address |= address_mask;
-            } else {
-                address &= !address_mask;
-            }
-            self.write_bit(chosen_bit, delay)?;
-        }
-        crc::check_crc8(&address.to_le_bytes())?;
-        Ok(Some((
-            Address(address),
-            SearchState {
-                address,
-                discrepancies,
-                last_discrepancy_index,
             },
-        )))
+        };
+        if chosen_bit {
+            pass.address |= address_mask;
+        } else {
+            pass.address &= !address_mask;
+        }
+        self.write_bit(chosen_bit, delay)?;
+        pass.known_bits = bit_index + 1;
+        Ok(true)
     }
 }
 
-pub struct DeviceSearch<'a, 'b, T, D> {
+impl SearchState {
+    /// Creates a search state that continues a search from a known address prefix.
+    ///
+    /// The next `device_search` using this state follows the first `known_bits` bits of `address`
+    /// (in the order they are sent on the bus, starting from the least significant bit of the
+    /// family code), then searches normally from there. Only devices whose address starts with that
+    /// prefix are found, and the search finishes once all of them have been found. If no device
+    /// has the prefix, the search returns `None`.
+    ///
+    /// For example, a `known_bits` of 8 restricts the search to the family code of `address`,
+    /// and a `known_bits` of 64 only finds `address` itself.
+    ///
+    /// # Panics
+    /// If `known_bits` is 0 or greater than 64. A search without a prefix is started with a
+    /// `search_state` of `None` instead.
+    pub fn seed_from_address(address: &Address, known_bits: u8) -> SearchState {
+        assert!(
+            (1..=64).contains(&known_bits),
+            "known_bits must be between 1 and 64"
+        );
+        SearchState {
+            address: address.0 & (u64::MAX >> (64 - known_bits)),
+            discrepancies: 0,
+            last_discrepancy_index: known_bits,
+            known_bits,
+        }
+    }
+
+    /// The bits of the address that are known so far, with any unknown bits set to 0.
+    /// After a search pass finds a device, this is the address of that device
+    pub fn partial_address(&self) -> u64 {
+        match self.known_bits {
+            0 => 0,
+            known_bits => self.address & (u64::MAX >> (64 - known_bits)),
+        }
+    }
+
+    /// The number of bits of `partial_address` that are known, starting from the least significant
+    /// bit (the order they are sent on the bus). This is 64 once a search pass has found a device
+    pub fn known_bits(&self) -> u8 {
+        self.known_bits
+    }
+
+    /// Saves the state, so a search can be continued later with `from_bytes`.
+    /// The bytes are the address and the discrepancies (both little endian), followed by the last
+    /// discrepancy index
+    pub fn to_bytes(&self) -> [u8; 17] {
+        let mut bytes = [0; 17];
+        bytes[..8].copy_from_slice(&self.address.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.discrepancies.to_le_bytes());
+        bytes[16] = self.last_discrepancy_index;
+        bytes
+    }
+
+    /// Restores a state saved with `to_bytes`.
+    /// Returns `InvalidSearchState` if the last discrepancy index is greater than 64
+    pub fn from_bytes<E>(bytes: &[u8; 17]) -> OneWireResult<SearchState, E> {
+        let mut address = [0; 8];
+        address.copy_from_slice(&bytes[..8]);
+        let mut discrepancies = [0; 8];
+        discrepancies.copy_from_slice(&bytes[8..16]);
+        if bytes[16] > 64 {
+            return Err(OneWireError::InvalidSearchState);
+        }
+        Ok(SearchState::from_parts(
+            u64::from_le_bytes(address),
+            u64::from_le_bytes(discrepancies),
+            bytes[16],
+        ))
+    }
+
+    /// A state that was saved between search passes, which has either found a device (and knows
+    /// its whole address) or is seeded
+    fn from_parts(address: u64, discrepancies: u64, last_discrepancy_index: u8) -> SearchState {
+        let mut state = SearchState {
+            address,
+            discrepancies,
+            last_discrepancy_index,
+            known_bits: 64,
+        };
+        if state.is_seeded() {
+            state.known_bits = last_discrepancy_index;
+        }
+        state
+    }
+
+    /// True when there are no more devices to find.
+    /// A seeded state also has no discrepancies, but is distinguished by a non-zero index
+    fn is_finished(&self) -> bool {
+        self.discrepancies == 0 && self.last_discrepancy_index == 0
+    }
+
+    /// True for a state created by `seed_from_address` that hasn't been searched yet
+    fn is_seeded(&self) -> bool {
+        self.discrepancies == 0 && self.last_discrepancy_index != 0
+    }
+
+    /// The initial state of a search pass that continues from `previous`
+    fn next_pass(previous: Option<&SearchState>) -> SearchState {
+        match previous {
+            Some(previous) => SearchState {
+                address: previous.address,
+                //keep all discrepancies except the last one
+                discrepancies: previous.discrepancies
+                    & !1_u64
+                        .checked_shl(previous.last_discrepancy_index as u32)
+                        .unwrap_or(0),
+                last_discrepancy_index: 0,
+                known_bits: 0,
+            },
+            None => SearchState {
+                address: 0,
+                discrepancies: 0,
+                last_discrepancy_index: 0,
+                known_bits: 0,
+            },
+        }
+    }
+}
+
+pub struct DeviceSearch<'a, 'b, T, D> {
     onewire: &'a mut OneWire<T>,
     delay: &'b mut D,
     state: Option<SearchState>,
     finished: bool,
     only_alarming: bool,
+    skip_errors: bool,
+}
+
+impl<'a, 'b, T, E, D> DeviceSearch<'a, 'b, T, D>
+where
+    T: InputPin<Error = E>,
+    T: OutputPin<Error = E>,
+    D: DelayUs<u16>,
+{
+    /// Calls `read` with the bus for each device as it is found, and yields each address along with
+    /// the result of `read`. The search continues from where it left off after each read, so
+    /// `read` can freely communicate with the device (or any other device) on the bus
+    pub fn then_read<F, R>(self, read: F) -> ThenRead<'a, 'b, T, D, F>
+    where
+        F: FnMut(&Address, &mut OneWire<T>, &mut D) -> R,
+    {
+        ThenRead { search: self, read }
+    }
+
+    /// Keeps searching after an address fails its crc check, instead of ending the search.
+    /// The `CrcMismatch` is still yielded, and the search continues from the address as it was
+    /// received, so a single glitch while one device was sending its address doesn't hide the
+    /// devices after it. Any other error (such as `Timeout` or `PinError`) still ends the search
+    pub fn skip_errors(mut self) -> Self {
+        self.skip_errors = true;
+        self
+    }
 }
 
 impl<'a, 'b, T, E, D> Iterator for DeviceSearch<'a, 'b, T, D>
@@ -379,18 +1769,32 @@ where
         if self.finished {
             return None;
         }
-        let result =
-            self.onewire
-                .device_search(self.state.as_ref(), self.only_alarming, self.delay);
-        match result {
-            Ok(Some((address, search_state))) => {
-                self.state = Some(search_state);
+        let pass =
+            match self
+                .onewire
+                .search_pass(self.state.as_ref(), self.only_alarming, self.delay)
+            {
+                Ok(Some(pass)) => pass,
+                Ok(None) => {
+                    self.state = None;
+                    self.finished = true;
+                    return None;
+                }
+                Err(err) => {
+                    self.state = None;
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+            };
+        let address = Address(pass.address);
+        match self.onewire.check_address_crc(pass.address) {
+            Ok(()) => {
+                self.state = Some(pass);
                 Some(Ok(address))
             }
-            Ok(None) => {
-                self.state = None;
-                self.finished = true;
-                None
+            Err(err) if self.skip_errors => {
+                self.state = Some(pass);
+                Some(Err(err))
             }
             Err(err) => {
                 self.state = None;
@@ -400,3 +1804,1911 @@ where
         }
     }
 }
+
+/// An iterator that reads from each device as it is found. See `DeviceSearch::then_read`
+pub struct ThenRead<'a, 'b, T, D, F> {
+    search: DeviceSearch<'a, 'b, T, D>,
+    read: F,
+}
+
+impl<'a, 'b, T, E, D, F, R> Iterator for ThenRead<'a, 'b, T, D, F>
+where
+    T: InputPin<Error = E>,
+    T: OutputPin<Error = E>,
+    D: DelayUs<u16>,
+    F: FnMut(&Address, &mut OneWire<T>, &mut D) -> R,
+{
+    type Item = OneWireResult<(Address, R), E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let address = match self.search.next()? {
+            Ok(address) => address,
+            Err(err) => return Some(Err(err)),
+        };
+        let output = (self.read)(&address, self.search.onewire, self.search.delay);
+        Some(Ok((address, output)))
+    }
+}
+
+/// Number of search triplets performed by each call to `IterativeSearch::step`
+const SEARCH_STEP_BITS: u8 = 8;
+
+/// The outcome of a single `IterativeSearch::step`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SearchStep {
+    /// A search pass completed and found a device
+    Found(Address),
+
+    /// Progress was made, but the current search pass isn't complete yet
+    Continue,
+
+    /// All devices have been found
+    Done,
+}
+
+/// A device search that is driven manually, one bounded step at a time.
+///
+/// Each step sends either the reset / search command, or a fixed number of search triplets,
+/// so the search can be spread across many scheduler time slices with a predictable cost per step.
+/// Devices are found in the same order as `OneWire::device_search`
+pub struct IterativeSearch<'a, T> {
+    onewire: &'a mut OneWire<T>,
+    previous: Option<SearchState>,
+    // the search pass in progress, along with the index of the next bit to search
+    pass: Option<(SearchState, u8)>,
+    only_alarming: bool,
+    finished: bool,
+}
+
+impl<'a, T, E> IterativeSearch<'a, T>
+where
+    T: InputPin<Error = E>,
+    T: OutputPin<Error = E>,
+{
+    /// Advances the search by a bounded amount of work.
+    /// After an error is returned, the search is finished and will only return `SearchStep::Done`
+    pub fn step(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<SearchStep, E> {
+        if self.finished {
+            return Ok(SearchStep::Done);
+        }
+        let result = self.try_step(delay);
+        match result {
+            Ok(SearchStep::Done) | Err(_) => {
+                self.finished = true;
+                self.pass = None;
+            }
+            _ => {}
+        }
+        result
+    }
+
+    /// The state of the search pass in progress, if any. This can be used to show the progress of
+    /// a slow search, with `SearchState::partial_address` and `SearchState::known_bits`
+    pub fn pass_state(&self) -> Option<&SearchState> {
+        self.pass.as_ref().map(|(pass, _)| pass)
+    }
+
+    fn try_step(&mut self, delay: &mut impl DelayUs<u16>) -> OneWireResult<SearchStep, E> {
+        let (mut pass, start_bit) = match self.pass.take() {
+            Some(pass) => pass,
+            None => {
+                if !self
+                    .onewire
+                    .start_search(self.previous.as_ref(), self.only_alarming, delay)?
+                {
+                    return Ok(SearchStep::Done);
+                }
+                self.pass = Some((SearchState::next_pass(self.previous.as_ref()), 0));
+                return Ok(SearchStep::Continue);
+            }
+        };
+        let end_bit = core::cmp::min(start_bit + SEARCH_STEP_BITS, 64);
+        for bit_index in start_bit..end_bit {
+            if !self
+                .onewire
+                .search_bit(bit_index, self.previous.as_ref(), &mut pass, delay)?
+            {
+                return Ok(SearchStep::Done);
+            }
+        }
+        if end_bit < 64 {
+            self.pass = Some((pass, end_bit));
+            return Ok(SearchStep::Continue);
+        }
+        self.onewire.check_address_crc(pass.address)?;
+        self.onewire.end_transaction(delay)?;
+        let address = Address(pass.address);
+        self.previous = Some(pass);
+        Ok(SearchStep::Found(address))
+    }
+}
+
+pub struct ReadStream<'a, 'b, T, D> {
+    onewire: &'a mut OneWire<T>,
+    delay: &'b mut D,
+    remaining: usize,
+}
+
+impl<'a, 'b, T, E, D> Iterator for ReadStream<'a, 'b, T, D>
+where
+    T: InputPin<Error = E>,
+    T: OutputPin<Error = E>,
+    D: DelayUs<u16>,
+{
+    type Item = OneWireResult<u8, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let result = self.onewire.read_byte(self.delay);
+        if result.is_ok() {
+            self.remaining -= 1;
+        } else {
+            self.remaining = 0;
+        }
+        Some(result)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use crate::crc::Crc8;
+    use crate::mock::{BrokenPin, Edge, Event, MockBus, MockDevice, ReplayBus};
+    use crate::{
+        Address, BusLevel, OneWire, OneWireError, Phase, SearchState, SearchStep, Speed, Timing,
+    };
+    use core::cell::Cell;
+    use std::rc::Rc;
+    use std::vec;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_device_search() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0x0B00000B20687E28),
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let addresses: vec::Vec<_> = one_wire
+            .devices(false, &mut delay)
+            .map(|address| address.unwrap())
+            .collect();
+        assert_eq!(
+            addresses,
+            vec![
+                Address(0xE800000B1FCD1028),
+                Address(0x70000008AC851628),
+                Address(0x0B00000B20687E28),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_device_search_discrepancy_at_bit_0() {
+        // devices that only differ in the first bit sent. These don't have a valid crc
+        let bus = MockBus::new(vec![MockDevice::new(0x29), MockDevice::new(0x28)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        one_wire.set_skip_crc_check(true);
+        let mut delay = bus.delay();
+
+        let (address, state) = one_wire
+            .device_search(None, false, &mut delay)
+            .unwrap()
+            .unwrap();
+        assert_eq!(address, Address(0x28));
+        assert_eq!(state.discrepancies, 1);
+        assert_eq!(state.last_discrepancy_index, 0);
+
+        let (address, state) = one_wire
+            .device_search(Some(&state), false, &mut delay)
+            .unwrap()
+            .unwrap();
+        assert_eq!(address, Address(0x29));
+        assert_eq!(state.discrepancies, 0);
+        assert_eq!(state.last_discrepancy_index, 0);
+
+        assert!(one_wire
+            .device_search(Some(&state), false, &mut delay)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_reset_with_fallback() {
+        // these devices don't support overdrive, so will only respond to a standard speed reset
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        one_wire.set_speed(Speed::Overdrive);
+        assert_eq!(
+            one_wire.reset_with_fallback(&mut delay).unwrap(),
+            (true, Speed::Standard)
+        );
+        assert_eq!(
+            one_wire.reset_with_fallback(&mut delay).unwrap(),
+            (true, Speed::Standard)
+        );
+    }
+
+    #[test]
+    fn test_first_discrepancy() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        assert_eq!(one_wire.first_discrepancy(&mut bus.delay()).unwrap(), None);
+
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        // the family codes match, and 0x10 / 0x16 differ at bit 1 of the second byte
+        assert_eq!(
+            one_wire.first_discrepancy(&mut bus.delay()).unwrap(),
+            Some(9)
+        );
+    }
+
+    #[test]
+    fn test_read_rom() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        assert_eq!(
+            one_wire.read_rom_expect(0x28, &mut delay).unwrap(),
+            Address(0xE800000B1FCD1028)
+        );
+        assert!(matches!(
+            one_wire.read_rom_expect(0x10, &mut delay),
+            Err(OneWireError::WrongFamily {
+                expected: 0x10,
+                found: 0x28
+            })
+        ));
+    }
+
+    #[test]
+    fn test_read_rom_multiple_devices() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        assert!(matches!(
+            one_wire.read_rom(&mut delay),
+            Err(OneWireError::CrcMismatch { .. })
+        ));
+
+        let bus = MockBus::new(vec![]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        assert!(matches!(
+            one_wire.read_rom(&mut bus.delay()),
+            Err(OneWireError::NoDevicePresent)
+        ));
+    }
+
+    #[test]
+    fn test_alarm_search() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028).alarming(),
+            MockDevice::new(0x70000008AC851628),
+            MockDevice::new(0x0B00000B20687E28).alarming(),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let addresses: vec::Vec<_> = one_wire
+            .devices(true, &mut delay)
+            .map(|address| address.unwrap())
+            .collect();
+        assert_eq!(
+            addresses,
+            vec![Address(0xE800000B1FCD1028), Address(0x0B00000B20687E28)]
+        );
+    }
+
+    #[test]
+    fn test_fast_select() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028).overdrive_capable(),
+            MockDevice::new(0x70000008AC851628).overdrive_capable(),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let mut selected = one_wire
+            .fast_select(&Address(0x70000008AC851628), &mut delay)
+            .unwrap();
+        selected.send_command(0x44, &mut delay).unwrap();
+        selected.send_command(0xBE, &mut delay).unwrap();
+        assert_eq!(bus.received(0), Vec::<u8>::new());
+        assert_eq!(bus.received(1), vec![0x44, 0xBE]);
+    }
+
+    #[test]
+    fn test_send_command_replay() {
+        let bus = ReplayBus::new(vec![
+            Event::Reset { present: true },
+            Event::Write(0x55),
+            Event::Write(0x28),
+            Event::Write(0x10),
+            Event::Write(0xCD),
+            Event::Write(0x1F),
+            Event::Write(0x0B),
+            Event::Write(0x00),
+            Event::Write(0x00),
+            Event::Write(0xE8),
+            Event::Write(0xBE),
+            Event::Read(0x50),
+            Event::Read(0x05),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        one_wire
+            .send_command(0xBE, Some(&Address(0xE800000B1FCD1028)), &mut delay)
+            .unwrap();
+        let mut output = [0; 2];
+        one_wire.read_bytes(&mut output, &mut delay).unwrap();
+        assert_eq!(output, [0x50, 0x05]);
+        bus.finish();
+    }
+
+    #[test]
+    #[should_panic(expected = "event 1: expected Some(Write(CC)), but the master wrote 55")]
+    fn test_replay_mismatch() {
+        let bus = ReplayBus::new(vec![Event::Reset { present: true }, Event::Write(0xCC)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let _ = one_wire.send_command(0xBE, Some(&Address(0xE800000B1FCD1028)), &mut bus.delay());
+    }
+
+    #[test]
+    fn test_exit_overdrive() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028).overdrive_capable()]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        one_wire
+            .fast_select(&Address(0xE800000B1FCD1028), &mut delay)
+            .unwrap();
+        assert!(one_wire.exit_overdrive(&mut delay).unwrap());
+        assert_eq!(
+            one_wire.read_rom(&mut delay).unwrap(),
+            Address(0xE800000B1FCD1028)
+        );
+    }
+
+    #[test]
+    fn test_find_device() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x0B00000B20687E28),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        assert!(one_wire
+            .find_device(&Address(0x0B00000B20687E28), &mut delay)
+            .unwrap());
+        assert!(!one_wire
+            .find_device(&Address(0x70000008AC851628), &mut delay)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_family_timing() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x0000000000000110),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+
+        // too long for a device to see a 1 bit, which makes the override visible
+        let slow = Timing {
+            write_1_low_us: 20,
+            ..Timing::STANDARD
+        };
+        one_wire
+            .set_family_timing(0x10, Speed::Standard, slow)
+            .unwrap();
+
+        for (index, address) in [0xE800000B1FCD1028, 0x0000000000000110].iter().enumerate() {
+            one_wire.reset(&mut delay).unwrap();
+            one_wire
+                .match_address(&Address(*address), &mut delay)
+                .unwrap();
+            one_wire.write_bytes(&[0xFF], &mut delay).unwrap();
+            let expected = if index == 0 { 0xFF } else { 0x00 };
+            assert_eq!(bus.received(index), vec![expected]);
+        }
+
+        for family in 0x20..0x23 {
+            one_wire
+                .set_family_timing(family, Speed::Standard, slow)
+                .unwrap();
+        }
+        one_wire
+            .set_family_timing(0x10, Speed::Standard, Timing::STANDARD)
+            .unwrap();
+        assert!(matches!(
+            one_wire.set_family_timing(0x30, Speed::Standard, slow),
+            Err(OneWireError::BufferTooSmall)
+        ));
+        one_wire.clear_family_timing(0x20);
+        one_wire
+            .set_family_timing(0x30, Speed::Standard, slow)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_family_timing_speed() {
+        let bus = MockBus::new(vec![MockDevice::new(0x0000000000000110).overdrive_capable()]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let address = Address(0x0000000000000110);
+
+        // standard speed timing is not used at overdrive speed
+        let slow = Timing {
+            write_1_low_us: 20,
+            ..Timing::STANDARD
+        };
+        one_wire
+            .set_family_timing(0x10, Speed::Standard, slow)
+            .unwrap();
+        let mut selected = one_wire.fast_select(&address, &mut delay).unwrap();
+        selected.bus().write_bytes(&[0xFF], &mut delay).unwrap();
+        assert_eq!(bus.received(0), vec![0xFF]);
+
+        // but overdrive timing is
+        let slow = Timing {
+            write_1_low_us: 4,
+            ..Timing::OVERDRIVE
+        };
+        one_wire
+            .set_family_timing(0x10, Speed::Overdrive, slow)
+            .unwrap();
+        one_wire.reset(&mut delay).unwrap();
+        one_wire.match_address(&address, &mut delay).unwrap();
+        one_wire.write_bytes(&[0xFF], &mut delay).unwrap();
+        assert_eq!(bus.received(0), vec![0xFF, 0x00]);
+
+        // and changing the speed stops using it
+        one_wire.set_speed(Speed::Standard);
+        assert_eq!(one_wire.timing(), &Timing::STANDARD);
+    }
+
+    #[test]
+    fn test_reset_estimate_population() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let (present, low_us) = one_wire.reset_estimate_population(&mut delay).unwrap();
+        assert!(present);
+        assert!(low_us > 0 && low_us <= 120);
+
+        let bus = MockBus::new(vec![]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        assert_eq!(
+            one_wire.reset_estimate_population(&mut delay).unwrap(),
+            (false, 0)
+        );
+    }
+
+    #[test]
+    fn test_read_status() {
+        #[derive(Debug, PartialEq)]
+        struct Status {
+            busy: bool,
+            error: bool,
+        }
+
+        impl From<u8> for Status {
+            fn from(byte: u8) -> Self {
+                Status {
+                    busy: byte & 0x01 != 0,
+                    error: byte & 0x80 != 0,
+                }
+            }
+        }
+
+        let bus = ReplayBus::new(vec![
+            Event::Reset { present: true },
+            Event::Write(0xCC),
+            Event::Write(0xAA),
+            Event::Read(0x01),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let status: Status = one_wire.read_status(None, 0xAA, &mut bus.delay()).unwrap();
+        assert_eq!(
+            status,
+            Status {
+                busy: true,
+                error: false
+            }
+        );
+        bus.finish();
+    }
+
+    #[test]
+    fn test_overdrive_reset() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028).overdrive_capable()]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+
+        let start = bus.now();
+        assert!(one_wire.reset(&mut delay).unwrap());
+        let standard_us = bus.now() - start;
+
+        one_wire
+            .fast_select(&Address(0xE800000B1FCD1028), &mut delay)
+            .unwrap();
+        let start = bus.now();
+        assert!(one_wire.reset(&mut delay).unwrap());
+        let overdrive_us = bus.now() - start;
+        assert!(overdrive_us * 5 < standard_us);
+
+        // the device is still at overdrive speed
+        one_wire.skip_address(&mut delay).unwrap();
+        one_wire.write_bytes(&[0x44], &mut delay).unwrap();
+        assert_eq!(bus.received(0), vec![0x44]);
+    }
+
+    #[test]
+    fn test_check_rise_time() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        assert_eq!(one_wire.measure_rise_time(&mut delay).unwrap(), 0);
+        assert!(one_wire
+            .check_rise_time(Speed::Standard, &mut delay)
+            .unwrap());
+        assert!(one_wire
+            .check_rise_time(Speed::Overdrive, &mut delay)
+            .unwrap());
+        assert!(one_wire.reset(&mut delay).unwrap());
+
+        // still low after the recovery timeout
+        let now = bus.now();
+        bus.hold_low(now + 1, now + 1000);
+        assert!(matches!(
+            one_wire.measure_rise_time(&mut delay),
+            Err(OneWireError::Timeout)
+        ));
+    }
+
+    #[test]
+    fn test_classify_by_speed() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628).overdrive_capable(),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let mut output = [(Address(0), Speed::Standard); 2];
+        assert_eq!(
+            one_wire.classify_by_speed(&mut output, &mut delay).unwrap(),
+            2
+        );
+        assert_eq!(
+            output,
+            [
+                (Address(0xE800000B1FCD1028), Speed::Standard),
+                (Address(0x70000008AC851628), Speed::Overdrive),
+            ]
+        );
+
+        let mut output = [(Address(0), Speed::Standard); 1];
+        assert!(matches!(
+            one_wire.classify_by_speed(&mut output, &mut delay),
+            Err(OneWireError::BufferTooSmall)
+        ));
+    }
+
+    #[test]
+    fn test_inter_triplet_delay() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        let mut delay = bus.delay();
+        let mut one_wire = OneWire::with_timing(bus.pin(), Timing::STANDARD).unwrap();
+        let start = bus.now();
+        one_wire.device_search(None, false, &mut delay).unwrap();
+        let without_gap = bus.now() - start;
+
+        let timing = Timing {
+            inter_triplet_us: 100,
+            ..Timing::STANDARD
+        };
+        let mut one_wire = OneWire::with_timing(bus.pin(), timing).unwrap();
+        let start = bus.now();
+        let (address, _) = one_wire
+            .device_search(None, false, &mut delay)
+            .unwrap()
+            .unwrap();
+        assert_eq!(address, Address(0xE800000B1FCD1028));
+        assert_eq!(bus.now() - start, without_gap + 63 * 100);
+    }
+
+    #[test]
+    fn test_read_rom_and_select() {
+        let bus = MockBus::new(vec![MockDevice::new(0x70000008AC851628)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let (address, mut selected) = one_wire.read_rom_and_select(&mut delay).unwrap();
+        assert_eq!(address, Address(0x70000008AC851628));
+        assert_eq!(selected.address(), address);
+        selected.send_command(0x44, &mut delay).unwrap();
+        selected.send_command(0xBE, &mut delay).unwrap();
+        assert_eq!(bus.received(0), vec![0x44, 0xBE]);
+    }
+
+    #[test]
+    fn test_pin_error_phase() {
+        let fail = Rc::new(Cell::new(false));
+        let mut one_wire = OneWire::new(BrokenPin { fail: fail.clone() }).unwrap();
+        let mut delay = MockBus::new(vec![]).delay();
+        fail.set(true);
+
+        let phase = |error| match error {
+            OneWireError::PinError { error: (), phase } => phase,
+            _ => panic!("expected a pin error"),
+        };
+        assert_eq!(phase(one_wire.reset(&mut delay).unwrap_err()), Phase::Reset);
+        assert_eq!(
+            phase(one_wire.skip_address(&mut delay).unwrap_err()),
+            Phase::Address
+        );
+        assert_eq!(
+            phase(one_wire.write_bytes(&[0x44], &mut delay).unwrap_err()),
+            Phase::DataWrite
+        );
+        assert_eq!(
+            phase(one_wire.read_byte(&mut delay).unwrap_err()),
+            Phase::DataRead
+        );
+    }
+
+    #[test]
+    fn test_scan_passive() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028).alarming(),
+            MockDevice::new(0x70000008AC851628),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let addresses: Vec<Address> = one_wire
+            .scan_passive(&mut delay)
+            .map(|address| address.unwrap())
+            .collect();
+        assert_eq!(
+            addresses,
+            vec![Address(0xE800000B1FCD1028), Address(0x70000008AC851628)]
+        );
+        assert!(bus.received(0).is_empty());
+        assert!(bus.received(1).is_empty());
+    }
+
+    #[test]
+    fn test_then_read() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let results: Vec<_> = one_wire
+            .devices(false, &mut delay)
+            .then_read(|address, bus, delay| {
+                bus.send_command(address.family_code(), Some(address), delay)
+            })
+            .map(|result| result.unwrap())
+            .collect();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, Address(0xE800000B1FCD1028));
+        assert_eq!(results[1].0, Address(0x70000008AC851628));
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+        assert_eq!(bus.received(0), vec![0x28]);
+        assert_eq!(bus.received(1), vec![0x28]);
+    }
+
+    #[test]
+    fn test_read_byte_crc() {
+        let data = [99, 1, 75, 70, 127, 255, 13, 16, 21];
+        let mut events = vec![Event::Reset { present: true }, Event::Write(0xCC)];
+        events.extend(data.iter().map(|byte| Event::Read(*byte)));
+        let bus = ReplayBus::new(events);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        one_wire.reset(&mut delay).unwrap();
+        one_wire.skip_address(&mut delay).unwrap();
+
+        let mut crc = Crc8::new();
+        for byte in &data {
+            assert_eq!(one_wire.read_byte_crc(&mut crc, &mut delay).unwrap(), *byte);
+        }
+        assert_eq!(crc.value(), 0);
+        bus.finish();
+    }
+
+    #[test]
+    fn test_new_with_recovery() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        bus.hold_low(0, 100);
+        let mut delay = bus.delay();
+        let mut one_wire = OneWire::new_with_recovery(bus.pin(), &mut delay).unwrap();
+        assert!(one_wire.reset(&mut delay).unwrap());
+
+        let bus = MockBus::new(vec![]);
+        bus.hold_low(0, u64::MAX);
+        assert!(matches!(
+            OneWire::new_with_recovery(bus.pin(), &mut bus.delay()),
+            Err(OneWireError::BusStuckLow)
+        ));
+    }
+
+    #[test]
+    fn test_partial_address() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let mut search = one_wire.iterative_search(false);
+        assert!(search.pass_state().is_none());
+        // the first step only starts the search pass
+        for known_bits in &[0, 8] {
+            assert!(matches!(
+                search.step(&mut delay).unwrap(),
+                SearchStep::Continue
+            ));
+            assert_eq!(search.pass_state().unwrap().known_bits(), *known_bits);
+        }
+        let pass = search.pass_state().unwrap();
+        assert_eq!(pass.known_bits(), 8);
+        assert_eq!(pass.partial_address(), 0x28);
+
+        let (_, state) = one_wire
+            .device_search(None, false, &mut delay)
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.known_bits(), 64);
+        assert_eq!(state.partial_address(), 0xE800000B1FCD1028);
+
+        let seeded = SearchState::seed_from_address(&Address(0xE800000B1FCD1028), 12);
+        assert_eq!(seeded.known_bits(), 12);
+        assert_eq!(seeded.partial_address(), 0x028);
+    }
+
+    #[test]
+    fn test_read_scratchpad() {
+        let scratchpad = [99, 1, 75, 70, 127, 255, 13, 16, 21];
+        let replay = |last: u8| {
+            let mut events = vec![Event::Reset { present: true }, Event::Write(0x55)];
+            events.extend(
+                Address(0xE800000B1FCD1028)
+                    .to_le_bytes()
+                    .iter()
+                    .map(|byte| Event::Write(*byte)),
+            );
+            events.push(Event::Write(0xBE));
+            events.extend(scratchpad[..8].iter().map(|byte| Event::Read(*byte)));
+            events.push(Event::Read(last));
+            ReplayBus::new(events)
+        };
+
+        let bus = replay(21);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        assert_eq!(
+            one_wire
+                .read_scratchpad(&Address(0xE800000B1FCD1028), &mut bus.delay())
+                .unwrap(),
+            scratchpad
+        );
+        bus.finish();
+
+        let bus = replay(20);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        assert!(matches!(
+            one_wire.read_scratchpad(&Address(0xE800000B1FCD1028), &mut bus.delay()),
+            Err(OneWireError::CrcMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_reset_collision_aware() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        assert!(one_wire.reset_collision_aware(&mut delay).unwrap());
+
+        // another master starts a reset first
+        let now = bus.now();
+        bus.hold_low(now, now + 480);
+        assert!(matches!(
+            one_wire.reset_collision_aware(&mut delay),
+            Err(OneWireError::BusContention)
+        ));
+    }
+
+    #[test]
+    fn test_read_until() {
+        let bus = ReplayBus::new(vec![
+            Event::Read(0x48),
+            Event::Read(0x69),
+            Event::Read(0x00),
+            Event::Read(0x41),
+            Event::Read(0x42),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let mut output = [0; 4];
+        assert_eq!(
+            one_wire.read_until(0x00, &mut output, &mut delay).unwrap(),
+            3
+        );
+        assert_eq!(output[..3], [0x48, 0x69, 0x00]);
+
+        let mut output = [0; 2];
+        assert!(matches!(
+            one_wire.read_until(0x00, &mut output, &mut delay),
+            Err(OneWireError::BufferTooSmall)
+        ));
+        bus.finish();
+    }
+
+    #[test]
+    fn test_configuration_getters() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028).overdrive_capable()]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        assert_eq!(one_wire.speed(), Speed::Standard);
+        assert_eq!(*one_wire.timing(), Timing::STANDARD);
+
+        one_wire
+            .fast_select(&Address(0xE800000B1FCD1028), &mut delay)
+            .unwrap();
+        assert_eq!(one_wire.speed(), Speed::Overdrive);
+        assert_eq!(*one_wire.timing(), Timing::OVERDRIVE);
+    }
+
+    #[test]
+    fn test_search_all_robust() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028).missing_from_searches(2)
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let mut output = [Address(0); 2];
+        assert!(matches!(
+            one_wire.search_all_robust(&mut output, 1, false, &mut delay),
+            Err(OneWireError::UnexpectedResponse)
+        ));
+        assert_eq!(
+            one_wire
+                .search_all_robust(&mut output, 1, false, &mut delay)
+                .unwrap(),
+            1
+        );
+        assert_eq!(output[0], Address(0xE800000B1FCD1028));
+    }
+
+    #[test]
+    fn test_max_search_duration() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628),
+            MockDevice::new(0x0B00000B20687E28),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        assert_eq!(one_wire.max_search_duration_us(1), 14960);
+
+        let start = bus.now();
+        assert_eq!(one_wire.devices(false, &mut delay).count(), 3);
+        assert!(bus.now() - start <= u64::from(one_wire.max_search_duration_us(3)));
+    }
+
+    #[test]
+    fn test_send_command_delayed() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let start = bus.now();
+        one_wire.send_command(0x44, None, &mut delay).unwrap();
+        let without_delay = bus.now() - start;
+
+        let start = bus.now();
+        one_wire
+            .send_command_delayed(0x44, None, 100_000, &mut delay)
+            .unwrap();
+        assert_eq!(bus.now() - start, without_delay + 100_000);
+        assert_eq!(bus.received(0), vec![0x44, 0x44]);
+    }
+
+    #[test]
+    fn test_search_stable() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628),
+            // missing from both passes of the first search
+            MockDevice::new(0x0B00000B20687E28).missing_from_searches(2),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let mut output = [Address(0); 3];
+        assert_eq!(
+            one_wire.search_stable(&mut output, 3, &mut delay).unwrap(),
+            2
+        );
+        assert_eq!(
+            output[..2],
+            [Address(0xE800000B1FCD1028), Address(0x70000008AC851628)]
+        );
+
+        assert_eq!(
+            one_wire.search_stable(&mut output, 3, &mut delay).unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_search_stable_device_drops_out() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628),
+            // found by the first search (three passes), then drops out of the last pass of the
+            // second search, which fails with UnexpectedResponse
+            MockDevice::new(0x0B00000B20687E28).missing_from_searches_after(5, 1),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let mut output = [Address(0); 3];
+        assert_eq!(
+            one_wire.search_stable(&mut output, 3, &mut delay).unwrap(),
+            2
+        );
+        assert_eq!(
+            output[..2],
+            [Address(0xE800000B1FCD1028), Address(0x70000008AC851628)]
+        );
+    }
+
+    #[test]
+    fn test_devices_iterator() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let mut devices = one_wire.devices(false, &mut delay);
+        assert_eq!(
+            devices.next().unwrap().unwrap(),
+            Address(0xE800000B1FCD1028)
+        );
+        assert_eq!(
+            devices.next().unwrap().unwrap(),
+            Address(0x70000008AC851628)
+        );
+        assert!(devices.next().is_none());
+        assert!(devices.next().is_none());
+
+        let bus = MockBus::new(vec![]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        assert!(one_wire.devices(false, &mut bus.delay()).next().is_none());
+    }
+
+    #[test]
+    fn test_devices_with_family() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x0000000012345610),
+            MockDevice::new(0x70000008AC851628),
+            MockDevice::new(0x000000001234563A),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        one_wire.set_skip_crc_check(true);
+        let mut delay = bus.delay();
+        let addresses: Vec<Address> = one_wire
+            .devices_with_family(0x28, false, &mut delay)
+            .map(|address| address.unwrap())
+            .collect();
+        assert_eq!(
+            addresses,
+            vec![Address(0xE800000B1FCD1028), Address(0x70000008AC851628)]
+        );
+        assert_eq!(
+            one_wire
+                .devices_with_family(0x3A, false, &mut delay)
+                .map(|address| address.unwrap())
+                .collect::<Vec<_>>(),
+            vec![Address(0x000000001234563A)]
+        );
+        assert_eq!(
+            one_wire
+                .devices_with_family(0x29, false, &mut delay)
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_count_devices() {
+        let addresses = [0xE800000B1FCD1028, 0x70000008AC851628, 0x0B00000B20687E28];
+        for count in 0..=addresses.len() {
+            let bus = MockBus::new(
+                addresses[..count]
+                    .iter()
+                    .map(|address| MockDevice::new(*address))
+                    .collect(),
+            );
+            let mut one_wire = OneWire::new(bus.pin()).unwrap();
+            assert_eq!(
+                one_wire.count_devices(false, &mut bus.delay()).unwrap(),
+                count
+            );
+        }
+
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1029)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        assert!(matches!(
+            one_wire.count_devices(false, &mut bus.delay()),
+            Err(OneWireError::CrcMismatch { .. })
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_search_state_serde() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628),
+            MockDevice::new(0x0B00000B20687E28),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let (_, state) = one_wire
+            .device_search(None, false, &mut delay)
+            .unwrap()
+            .unwrap();
+        let saved = serde_json::to_string(&state).unwrap();
+        assert_eq!(
+            saved,
+            r#"{"address":16717361864577454120,"discrepancies":512,"last_discrepancy_index":9}"#
+        );
+
+        let restored: SearchState = serde_json::from_str(&saved).unwrap();
+        assert_eq!(restored.known_bits(), 64);
+        let (address, _) = one_wire
+            .device_search(Some(&restored), false, &mut delay)
+            .unwrap()
+            .unwrap();
+        assert_eq!(address, Address(0x70000008AC851628));
+        assert_eq!(
+            serde_json::to_string(&address).unwrap(),
+            "8070450569502070312"
+        );
+    }
+
+    #[test]
+    fn test_search_state_bytes() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let (_, state) = one_wire
+            .device_search(None, false, &mut delay)
+            .unwrap()
+            .unwrap();
+        let bytes = state.to_bytes();
+        assert_eq!(
+            bytes,
+            [0x28, 0x10, 0xCD, 0x1F, 0x0B, 0x00, 0x00, 0xE8, 0x00, 0x02, 0, 0, 0, 0, 0, 0, 9]
+        );
+
+        let restored = SearchState::from_bytes::<()>(&bytes).unwrap();
+        let (address, _) = one_wire
+            .device_search(Some(&restored), false, &mut delay)
+            .unwrap()
+            .unwrap();
+        assert_eq!(address, Address(0x70000008AC851628));
+
+        let mut invalid = bytes;
+        invalid[16] = 65;
+        assert!(matches!(
+            SearchState::from_bytes::<()>(&invalid),
+            Err(OneWireError::InvalidSearchState)
+        ));
+    }
+
+    #[test]
+    fn test_device_search_bounded() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628),
+            MockDevice::new(0x0B00000B20687E28),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let mut output = [Address(0); 2];
+        let (count, state) = one_wire
+            .device_search_bounded(None, false, &mut [], &mut delay)
+            .unwrap();
+        assert_eq!(count, 0);
+        assert!(state.is_none());
+
+        let (count, state) = one_wire
+            .device_search_bounded(None, false, &mut output, &mut delay)
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(
+            output,
+            [Address(0xE800000B1FCD1028), Address(0x70000008AC851628)]
+        );
+
+        let (count, state) = one_wire
+            .device_search_bounded(state.as_ref(), false, &mut output, &mut delay)
+            .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(output[0], Address(0x0B00000B20687E28));
+
+        let (count, _) = one_wire
+            .device_search_bounded(state.as_ref(), false, &mut output, &mut delay)
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_sorted_addresses_match_search_order() {
+        let mut expected = vec![
+            Address(0x0B00000B20687E28),
+            Address(0x70000008AC851628),
+            Address(0xE800000B1FCD1028),
+        ];
+        let bus = MockBus::new(
+            expected
+                .iter()
+                .map(|address| MockDevice::new(address.0))
+                .collect(),
+        );
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let found: Vec<Address> = one_wire
+            .devices(false, &mut bus.delay())
+            .map(|address| address.unwrap())
+            .collect();
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_send_command_resume() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        assert!(matches!(
+            one_wire.send_command_resume(0x44, &mut delay),
+            Err(OneWireError::NoDeviceSelected)
+        ));
+
+        one_wire
+            .send_command(0x44, Some(&Address(0x70000008AC851628)), &mut delay)
+            .unwrap();
+        one_wire.send_command_resume(0xBE, &mut delay).unwrap();
+        assert_eq!(bus.received(0), Vec::<u8>::new());
+        assert_eq!(bus.received(1), vec![0x44, 0xBE]);
+
+        one_wire.send_command(0x44, None, &mut delay).unwrap();
+        assert!(matches!(
+            one_wire.resume(&mut delay),
+            Err(OneWireError::NoDeviceSelected)
+        ));
+    }
+
+    #[test]
+    fn test_read_power_supply() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628).parasite_powered(),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        assert!(!one_wire.read_power_supply(None, &mut delay).unwrap());
+        assert!(one_wire
+            .read_power_supply(Some(&Address(0xE800000B1FCD1028)), &mut delay)
+            .unwrap());
+        assert!(!one_wire
+            .read_power_supply(Some(&Address(0x70000008AC851628)), &mut delay)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_is_present() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x0B00000B20687E28),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        assert!(one_wire
+            .is_present(&Address(0xE800000B1FCD1028), &mut delay)
+            .unwrap());
+        assert!(one_wire
+            .is_present(&Address(0x0B00000B20687E28), &mut delay)
+            .unwrap());
+        assert!(!one_wire
+            .is_present(&Address(0x70000008AC851628), &mut delay)
+            .unwrap());
+
+        let bus = MockBus::new(vec![]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        assert!(!one_wire
+            .is_present(&Address(0xE800000B1FCD1028), &mut bus.delay())
+            .unwrap());
+    }
+
+    #[test]
+    fn test_recovery_timeout() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let now = bus.now();
+        bus.hold_low(now, now + 400);
+        assert!(matches!(
+            one_wire.reset(&mut delay),
+            Err(OneWireError::Timeout)
+        ));
+
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        one_wire.set_recovery_timeout(1000);
+        let now = bus.now();
+        bus.hold_low(now, now + 400);
+        assert!(one_wire.reset(&mut delay).unwrap());
+    }
+
+    #[test]
+    fn test_with_strong_pullup() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028).parasite_powered()]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        one_wire.send_command(0x44, None, &mut delay).unwrap();
+        let mut pullup = vec![];
+        let start = bus.now();
+        one_wire
+            .with_strong_pullup(
+                750_000,
+                |enable| pullup.push((enable, bus.now())),
+                &mut delay,
+            )
+            .unwrap();
+        assert_eq!(pullup, vec![(true, start), (false, start + 750_000)]);
+    }
+
+    #[test]
+    fn test_write_byte_with_power() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028).parasite_powered()]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        one_wire.reset(&mut delay).unwrap();
+        one_wire.skip_address(&mut delay).unwrap();
+        let mut pullup = vec![];
+        let start = bus.now();
+        one_wire
+            .write_byte_with_power(
+                0x44,
+                750_000,
+                |enable| pullup.push((enable, bus.now())),
+                &mut delay,
+            )
+            .unwrap();
+        // 7 full slots, then the low part of the last (0) bit, with no recovery time
+        let enabled = start + 7 * 70 + 60;
+        assert_eq!(pullup, vec![(true, enabled), (false, enabled + 750_000)]);
+        assert_eq!(bus.received(0), vec![0x44]);
+    }
+
+    #[test]
+    fn test_bus_short_circuit() {
+        // the default recovery timeout, after which the bus gets `SHORT_CIRCUIT_US` more to rise
+        let short_after = 250 + u64::from(crate::SHORT_CIRCUIT_US);
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+
+        // held low for a while, but rises before it counts as a short
+        let now = bus.now();
+        bus.hold_low(now, now + short_after - 50);
+        assert!(matches!(
+            one_wire.reset(&mut delay),
+            Err(OneWireError::Timeout)
+        ));
+        one_wire.reset(&mut delay).unwrap();
+        let now = bus.now();
+        bus.hold_low(now, now + short_after - 50);
+        assert!(matches!(
+            one_wire.read_bit(&mut delay),
+            Err(OneWireError::Timeout)
+        ));
+
+        let now = bus.now();
+        bus.hold_low(now, u64::MAX);
+        assert!(matches!(
+            one_wire.reset(&mut delay),
+            Err(OneWireError::BusShortCircuit)
+        ));
+        assert!(matches!(
+            one_wire.read_bit(&mut delay),
+            Err(OneWireError::BusShortCircuit)
+        ));
+    }
+
+    #[test]
+    fn test_reset_transcript() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        let (pin, transcript) = bus.recording_pin();
+        let mut one_wire = OneWire::new(pin).unwrap();
+        let mut delay = bus.delay();
+        transcript.clear();
+        assert!(one_wire.reset(&mut delay).unwrap());
+        assert_eq!(
+            transcript.relative_edges(),
+            vec![Edge::Low(0), Edge::High(480)]
+        );
+
+        // MATCH_ROM (0x55), then the address, least significant bit first
+        transcript.clear();
+        one_wire
+            .match_address(&Address(0xE800000B1FCD1028), &mut delay)
+            .unwrap();
+        let edges = transcript.relative_edges();
+        assert_eq!(edges.len(), 2 * 72);
+        assert_eq!(
+            edges[..8],
+            [
+                Edge::Low(0),
+                Edge::High(6),
+                Edge::Low(70),
+                Edge::High(130),
+                Edge::Low(140),
+                Edge::High(146),
+                Edge::Low(210),
+                Edge::High(270),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_scratchpad_crc8() {
+        let address = Address(0xE800000B1FCD1028);
+        let mut events = vec![Event::Reset { present: true }, Event::Write(0x55)];
+        events.extend(address.to_le_bytes().iter().map(|byte| Event::Write(*byte)));
+        events.push(Event::Write(0xBE));
+        // the ROM code example from Maxim application note 27
+        events.extend(
+            [0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00, 0xA2]
+                .iter()
+                .map(|byte| Event::Read(*byte)),
+        );
+        let bus = ReplayBus::new(events);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        assert_eq!(
+            one_wire
+                .read_scratchpad_crc8::<8>(&address, 0xBE, &mut bus.delay())
+                .unwrap(),
+            [0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00, 0xA2]
+        );
+        bus.finish();
+    }
+
+    #[test]
+    fn test_copy_scratchpad() {
+        let address = Address(0xE800000B1FCD1028);
+        let mut events = vec![];
+        for command in &[0x48, 0x48, 0xB8] {
+            events.extend(&[Event::Reset { present: true }, Event::Write(0x55)]);
+            events.extend(address.to_le_bytes().iter().map(|byte| Event::Write(*byte)));
+            events.push(Event::Write(*command));
+        }
+        let bus = ReplayBus::new(events);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let start = bus.now();
+        one_wire.copy_scratchpad(&address, &mut delay).unwrap();
+        assert!(bus.now() - start >= 10_000);
+
+        let mut pullup = vec![];
+        one_wire
+            .copy_scratchpad_with_power(
+                &address,
+                |enable| pullup.push((enable, bus.now())),
+                &mut delay,
+            )
+            .unwrap();
+        assert_eq!(pullup.len(), 2);
+        assert_eq!(pullup[1].1 - pullup[0].1, 10_000);
+
+        one_wire.recall_e2(&address, &mut delay).unwrap();
+        bus.finish();
+    }
+
+    #[test]
+    fn test_devices_skip_errors() {
+        // the middle device has a corrupted crc
+        let devices = || {
+            vec![
+                MockDevice::new(0xE800000B1FCD1028),
+                MockDevice::new(0x71000008AC851628),
+                MockDevice::new(0x0B00000B20687E28),
+            ]
+        };
+        let bus = MockBus::new(devices());
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let results: Vec<_> = one_wire.devices(false, &mut delay).collect();
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[1], Err(OneWireError::CrcMismatch { .. })));
+
+        let bus = MockBus::new(devices());
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let results: Vec<_> = one_wire.devices(false, &mut delay).skip_errors().collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].unwrap(), Address(0xE800000B1FCD1028));
+        assert!(matches!(results[1], Err(OneWireError::CrcMismatch { .. })));
+        assert_eq!(results[2].unwrap(), Address(0x0B00000B20687E28));
+    }
+
+    #[test]
+    fn test_recover() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        // abandon a READ_ROM part way through the address
+        one_wire.reset(&mut delay).unwrap();
+        one_wire.write_byte(0x33, &mut delay).unwrap();
+        one_wire.read_byte(&mut delay).unwrap();
+        one_wire.recover(&mut delay).unwrap();
+        assert_eq!(
+            one_wire.expect_single(&mut delay).unwrap(),
+            Address(0xE800000B1FCD1028)
+        );
+
+        let now = bus.now();
+        bus.hold_low(now, u64::MAX);
+        assert!(matches!(
+            one_wire.recover(&mut delay),
+            Err(OneWireError::BusShortCircuit)
+        ));
+    }
+
+    #[test]
+    fn test_set_timing() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028).overdrive_capable()]);
+        let (pin, transcript) = bus.recording_pin();
+        let mut one_wire = OneWire::new(pin).unwrap();
+        let mut delay = bus.delay();
+        one_wire.set_timing(
+            Speed::Standard,
+            Timing {
+                reset_low_us: 600,
+                reset_recovery_us: 500,
+                ..Timing::STANDARD
+            },
+        );
+        transcript.clear();
+        assert!(one_wire.reset(&mut delay).unwrap());
+        assert!(one_wire.reset(&mut delay).unwrap());
+        assert_eq!(
+            transcript.relative_edges(),
+            vec![
+                Edge::Low(0),
+                Edge::High(600),
+                Edge::Low(1100),
+                Edge::High(1700)
+            ]
+        );
+
+        one_wire.set_timing(
+            Speed::Overdrive,
+            Timing {
+                reset_low_us: 60,
+                ..Timing::OVERDRIVE
+            },
+        );
+        one_wire.set_speed(Speed::Overdrive);
+        transcript.clear();
+        one_wire.reset(&mut delay).unwrap();
+        assert_eq!(
+            transcript.relative_edges(),
+            vec![Edge::Low(0), Edge::High(60)]
+        );
+    }
+
+    #[test]
+    fn test_short_recovery_timeout() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        for timeout in 0..=1 {
+            one_wire.set_recovery_timeout(timeout);
+            assert!(one_wire.reset(&mut delay).unwrap());
+        }
+
+        let now = bus.now();
+        bus.hold_low(now, now + 10);
+        assert!(matches!(
+            one_wire.reset(&mut delay),
+            Err(OneWireError::Timeout)
+        ));
+    }
+
+    #[test]
+    fn test_fast_select_readdresses_after_bus_use() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028).overdrive_capable(),
+            MockDevice::new(0x70000008AC851628).overdrive_capable(),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        // switch both devices to overdrive, so either can be addressed at overdrive speed
+        one_wire
+            .overdrive_convert_all(10, |_| {}, &mut delay)
+            .unwrap();
+        let mut selected = one_wire
+            .fast_select(&Address(0x70000008AC851628), &mut delay)
+            .unwrap();
+        selected.send_command(0x44, &mut delay).unwrap();
+        selected
+            .bus()
+            .send_command(0xBE, Some(&Address(0xE800000B1FCD1028)), &mut delay)
+            .unwrap();
+        selected.send_command(0x48, &mut delay).unwrap();
+        selected.send_command(0xB8, &mut delay).unwrap();
+        assert_eq!(bus.received(0), vec![0x44, 0xBE]);
+        assert_eq!(bus.received(1), vec![0x44, 0x44, 0x48, 0xB8]);
+    }
+
+    #[test]
+    fn test_read_rom_and_select_readdresses_after_bus_use() {
+        let mut events = vec![Event::Reset { present: true }, Event::Write(0x33)];
+        events.extend([0x28, 0x10, 0xCD, 0x1F, 0x0B, 0x00, 0x00, 0xE8].map(Event::Read));
+        events.extend([Event::Reset { present: true }, Event::Write(0xA5)]);
+        events.push(Event::Write(0x44));
+        events.extend([Event::Reset { present: true }, Event::Write(0x55)]);
+        events.extend([0x28, 0x16, 0x85, 0xAC, 0x08, 0x00, 0x00, 0x70].map(Event::Write));
+        events.push(Event::Write(0xBE));
+        events.extend([Event::Reset { present: true }, Event::Write(0x55)]);
+        events.extend([0x28, 0x10, 0xCD, 0x1F, 0x0B, 0x00, 0x00, 0xE8].map(Event::Write));
+        events.push(Event::Write(0x48));
+        events.extend([Event::Reset { present: true }, Event::Write(0xA5)]);
+        events.push(Event::Write(0xB8));
+        let bus = ReplayBus::new(events);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let (address, mut selected) = one_wire.read_rom_and_select(&mut delay).unwrap();
+        assert_eq!(address, Address(0xE800000B1FCD1028));
+        selected.send_command(0x44, &mut delay).unwrap();
+        selected
+            .bus()
+            .send_command(0xBE, Some(&Address(0x70000008AC851628)), &mut delay)
+            .unwrap();
+        selected.send_command(0x48, &mut delay).unwrap();
+        selected.send_command(0xB8, &mut delay).unwrap();
+        bus.finish();
+    }
+
+    #[test]
+    fn test_reset_per_byte_keeps_command() {
+        let address = [0x28, 0x16, 0x85, 0xAC, 0x08, 0x00, 0x00, 0x70];
+        let mut events = vec![Event::Reset { present: true }, Event::Write(0x55)];
+        events.extend(address.map(Event::Write));
+        events.extend([Event::Write(0x4E), Event::Write(0x01)]);
+        events.extend([Event::Reset { present: true }, Event::Write(0x55)]);
+        events.extend(address.map(Event::Write));
+        events.push(Event::Write(0x02));
+        events.extend([Event::Reset { present: true }, Event::Write(0x55)]);
+        events.extend(address.map(Event::Write));
+        events.push(Event::Read(0x50));
+        events.extend([Event::Reset { present: true }, Event::Write(0x55)]);
+        events.extend(address.map(Event::Write));
+        events.push(Event::Read(0x05));
+        let bus = ReplayBus::new(events);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        one_wire.set_reset_per_byte(true);
+        one_wire
+            .send_command(0x4E, Some(&Address(0x70000008AC851628)), &mut delay)
+            .unwrap();
+        one_wire.write_bytes(&[0x01, 0x02], &mut delay).unwrap();
+        one_wire.reset(&mut delay).unwrap();
+        one_wire
+            .match_address(&Address(0x70000008AC851628), &mut delay)
+            .unwrap();
+        let mut output = [0; 2];
+        one_wire.read_bytes(&mut output, &mut delay).unwrap();
+        assert_eq!(output, [0x50, 0x05]);
+        bus.finish();
+    }
+
+    #[test]
+    fn test_presence_window_sampling() {
+        let bus = MockBus::new(vec![]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+
+        // a presence pulse that starts well after the window opens is still seen
+        let release = bus.now() + 480;
+        bus.hold_low(release + 100, release + 200);
+        let (present, low_us) = one_wire.reset_estimate_population(&mut delay).unwrap();
+        assert!(present);
+        assert!((95..=105).contains(&low_us), "{}", low_us);
+
+        // a pulse that is over before the window opens is ignored
+        let release = bus.now() + 480;
+        bus.hold_low(release + 5, release + 50);
+        assert!(!one_wire.reset(&mut delay).unwrap());
+
+        // as is one that only starts after the window closed
+        let release = bus.now() + 480;
+        bus.hold_low(release + 300, release + 400);
+        assert!(!one_wire.reset(&mut delay).unwrap());
+    }
+
+    #[test]
+    fn test_presence_past_reset_recovery() {
+        use embedded_hal::blocking::delay::DelayUs;
+
+        let bus = MockBus::new(vec![]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let release = bus.now() + 480;
+        bus.hold_low(release + 100, release + 600);
+        assert!(matches!(
+            one_wire.reset(&mut delay),
+            Err(OneWireError::BusNotHigh)
+        ));
+        DelayUs::<u16>::delay_us(&mut delay, 200);
+        assert!(!one_wire.reset(&mut delay).unwrap());
+    }
+
+    #[test]
+    fn test_read_many() {
+        let first = [0x28, 0x10, 0xCD, 0x1F, 0x0B, 0x00, 0x00, 0xE8];
+        let second = [0x28, 0x16, 0x85, 0xAC, 0x08, 0x00, 0x00, 0x70];
+        let mut events = vec![Event::Reset { present: true }, Event::Write(0x55)];
+        events.extend(first.map(Event::Write));
+        events.extend([Event::Write(0xBE), Event::Read(0x01), Event::Read(0x02)]);
+        events.extend([Event::Reset { present: true }, Event::Write(0xA5)]);
+        events.extend([Event::Write(0xBE), Event::Read(0x03), Event::Read(0x04)]);
+        events.extend([Event::Reset { present: true }, Event::Write(0x55)]);
+        events.extend(second.map(Event::Write));
+        events.extend([Event::Write(0xBE), Event::Read(0x05), Event::Read(0x06)]);
+        let bus = ReplayBus::new(events);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let addresses = [
+            Address(0xE800000B1FCD1028),
+            Address(0xE800000B1FCD1028),
+            Address(0x70000008AC851628),
+        ];
+
+        let mut output = [0; 5];
+        assert!(matches!(
+            one_wire.read_many(&addresses, 0xBE, 2, &mut output, &mut delay),
+            Err(OneWireError::BufferTooSmall)
+        ));
+
+        let mut output = [0; 6];
+        one_wire
+            .read_many(&addresses, 0xBE, 2, &mut output, &mut delay)
+            .unwrap();
+        assert_eq!(output, [0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        bus.finish();
+    }
+
+    #[test]
+    fn test_overdrive_convert_all() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0xE800000B1FCD1028)
+                .overdrive_capable()
+                .parasite_powered(),
+            MockDevice::new(0x70000008AC851628)
+                .overdrive_capable()
+                .parasite_powered(),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        let mut pullup = vec![];
+        let start = bus.now();
+        one_wire
+            .overdrive_convert_all(
+                750_000,
+                |enable| pullup.push((enable, bus.now())),
+                &mut delay,
+            )
+            .unwrap();
+        // a standard speed reset and OVERDRIVE_SKIP_ROM, then CONVERT_T at overdrive speed: 7 full
+        // slots (five 0 bits and two 1 bits), and the low part of the last (0) bit
+        let enabled = start + 480 + 480 + 8 * 70 + 5 * 11 + 2 * 9 + 8;
+        assert_eq!(pullup, vec![(true, enabled), (false, enabled + 750_000)]);
+        assert_eq!(one_wire.speed(), Speed::Overdrive);
+        assert_eq!(bus.received(0), vec![0x44]);
+        assert_eq!(bus.received(1), vec![0x44]);
+
+        // already at overdrive speed, the devices are addressed with SKIP_ROM
+        pullup.clear();
+        let start = bus.now();
+        one_wire
+            .overdrive_convert_all(
+                750_000,
+                |enable| pullup.push((enable, bus.now())),
+                &mut delay,
+            )
+            .unwrap();
+        let enabled = start + 70 + 48 + 4 * 11 + 4 * 9 + 5 * 11 + 2 * 9 + 8;
+        assert_eq!(pullup, vec![(true, enabled), (false, enabled + 750_000)]);
+        assert_eq!(one_wire.speed(), Speed::Overdrive);
+        assert_eq!(bus.received(0), vec![0x44, 0x44]);
+        assert_eq!(bus.received(1), vec![0x44, 0x44]);
+    }
+
+    #[test]
+    fn test_read_streaming() {
+        let bus = ReplayBus::new(vec![
+            Event::Reset { present: true },
+            Event::Write(0xCC),
+            Event::Write(0xBE),
+            Event::Read(0x50),
+            Event::Read(0x05),
+            Event::Reset { present: true },
+            Event::Write(0xCC),
+            Event::Write(0x44),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+        one_wire.send_command(0xBE, None, &mut delay).unwrap();
+        // only the bytes that are iterated are read, the rest of the scratchpad is abandoned
+        {
+            let mut stream = one_wire.read_streaming(9, &mut delay);
+            assert_eq!(stream.next().map(Result::unwrap), Some(0x50));
+            assert_eq!(stream.next().map(Result::unwrap), Some(0x05));
+            assert_eq!(stream.size_hint(), (0, Some(7)));
+        }
+        one_wire.send_command(0x44, None, &mut delay).unwrap();
+        bus.finish();
+
+        // the stream ends after the first error
+        let fail = Rc::new(Cell::new(false));
+        let mut one_wire = OneWire::new(BrokenPin { fail: fail.clone() }).unwrap();
+        let mut delay = MockBus::new(vec![]).delay();
+        fail.set(true);
+        let mut stream = one_wire.read_streaming(9, &mut delay);
+        assert!(matches!(
+            stream.next(),
+            Some(Err(OneWireError::PinError { .. }))
+        ));
+        fail.set(false);
+        assert!(stream.next().is_none());
+        assert_eq!(stream.size_hint(), (0, Some(0)));
+    }
+
+    #[test]
+    fn test_inter_transaction_delay() {
+        let bus = MockBus::new(vec![MockDevice::new(0xE800000B1FCD1028)]);
+        let mut delay = bus.delay();
+        let address = Address(0xE800000B1FCD1028);
+
+        // a reset, MATCH_ROM with the address and RECALL_E2, with no gap afterwards
+        let mut one_wire = OneWire::with_timing(bus.pin(), Timing::STANDARD).unwrap();
+        let start = bus.now();
+        one_wire.recall_e2(&address, &mut delay).unwrap();
+        assert_eq!(bus.now() - start, 480 + 480 + 10 * 8 * 70);
+
+        let timing = Timing {
+            inter_transaction_us: 100,
+            ..Timing::STANDARD
+        };
+        let mut one_wire = OneWire::with_timing(bus.pin(), timing).unwrap();
+        let start = bus.now();
+        one_wire.recall_e2(&address, &mut delay).unwrap();
+        assert_eq!(bus.now() - start, 480 + 480 + 10 * 8 * 70 + 100);
+
+        // the gap follows every transaction of a multi-transaction call
+        let mut output = [0; 2];
+        let start = bus.now();
+        one_wire
+            .read_many(&[address, address], 0xBE, 1, &mut output, &mut delay)
+            .unwrap();
+        let with_gap = bus.now() - start;
+        let mut one_wire = OneWire::with_timing(bus.pin(), Timing::STANDARD).unwrap();
+        let start = bus.now();
+        one_wire
+            .read_many(&[address, address], 0xBE, 1, &mut output, &mut delay)
+            .unwrap();
+        assert_eq!(with_gap, bus.now() - start + 2 * 100);
+    }
+
+    #[test]
+    fn test_wait_for_idle() {
+        let bus = MockBus::new(vec![]);
+        let one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+
+        // an idle bus returns straight away
+        let start = bus.now();
+        one_wire.wait_for_idle(1_000, &mut delay).unwrap();
+        assert_eq!(bus.now(), start);
+
+        // the bus is released within the timeout
+        let start = bus.now();
+        bus.hold_low(start, start + 400);
+        one_wire.wait_for_idle(1_000, &mut delay).unwrap();
+        assert_eq!(bus.now(), start + 400);
+
+        // the bus is still held after the timeout
+        let start = bus.now();
+        bus.hold_low(start, start + 2_000);
+        assert!(matches!(
+            one_wire.wait_for_idle(1_000, &mut delay),
+            Err(OneWireError::BusBusy)
+        ));
+        assert_eq!(bus.now(), start + 1_000);
+    }
+
+    #[test]
+    fn test_bus_level() {
+        use embedded_hal::blocking::delay::DelayUs;
+
+        let bus = MockBus::new(vec![]);
+        let one_wire = OneWire::new(bus.pin()).unwrap();
+        assert_eq!(one_wire.bus_level().unwrap(), BusLevel::High);
+        let now = bus.now();
+        bus.hold_low(now, now + 100);
+        assert_eq!(one_wire.bus_level().unwrap(), BusLevel::Low);
+        DelayUs::<u16>::delay_us(&mut bus.delay(), 100);
+        assert_eq!(one_wire.bus_level().unwrap(), BusLevel::High);
+    }
+
+    #[test]
+    fn test_iterative_search_matches_device_search() {
+        let bus = MockBus::new(vec![
+            MockDevice::new(0x0B00000B20687E28).alarming(),
+            MockDevice::new(0xE800000B1FCD1028),
+            MockDevice::new(0x70000008AC851628).alarming(),
+        ]);
+        let mut one_wire = OneWire::new(bus.pin()).unwrap();
+        let mut delay = bus.delay();
+
+        for only_alarming in [false, true] {
+            let mut expected = Vec::new();
+            let mut search_state = None;
+            while let Some((address, state)) = one_wire
+                .device_search(search_state.as_ref(), only_alarming, &mut delay)
+                .unwrap()
+            {
+                expected.push(address);
+                search_state = Some(state);
+            }
+
+            let mut found = Vec::new();
+            let mut search = one_wire.iterative_search(only_alarming);
+            loop {
+                match search.step(&mut delay).unwrap() {
+                    SearchStep::Found(address) => found.push(address),
+                    SearchStep::Continue => {}
+                    SearchStep::Done => break,
+                }
+            }
+            assert_eq!(expected.len(), if only_alarming { 2 } else { 3 });
+            assert_eq!(found, expected);
+        }
+    }
+}
@@ -0,0 +1,761 @@
+//! A simulated 1-Wire bus, for testing without hardware.
+//!
+//! The simulation works at the level of individual time slots: the master's pin edges and delays
+//! are tracked on a shared clock, and each simulated device responds to the slots the same way a
+//! real device would (presence pulses, pulling the bus low to send a 0, sampling written bits).
+//!
+//! `MockBus` simulates devices with the given addresses, which respond to searches and commands,
+//! and `ReplayBus` checks the master against a recorded sequence of events. Outside of this crate,
+//! this module requires the `mock` feature, and `std`.
+//!
+//! ```
+//! use one_wire_bus::mock::{MockBus, MockDevice};
+//! use one_wire_bus::{Address, OneWire};
+//!
+//! let bus = MockBus::new(vec![
+//!     MockDevice::new(0xE800000B1FCD1028),
+//!     MockDevice::new(0x70000008AC851628),
+//! ]);
+//! let mut one_wire = OneWire::new(bus.pin()).unwrap();
+//! let devices: Vec<Address> = one_wire
+//!     .devices(false, &mut bus.delay())
+//!     .map(Result::unwrap)
+//!     .collect();
+//! assert_eq!(
+//!     devices,
+//!     [Address(0xE800000B1FCD1028), Address(0x70000008AC851628)]
+//! );
+//! ```
+
+extern crate std;
+
+use crate::{commands, Address};
+use core::cell::{Cell, RefCell};
+use core::convert::Infallible;
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use std::rc::Rc;
+use std::vec::Vec;
+
+/// Anything that responds to time slots on a simulated bus
+pub trait Slave {
+    /// Whether the device is currently communicating at overdrive speed
+    fn is_overdrive(&self) -> bool {
+        false
+    }
+
+    /// Called after a reset pulse of `low_us`. Returns true if the device sends a presence pulse
+    fn reset(&mut self, low_us: u64) -> bool;
+
+    /// Called at the start of a time slot. Returns the bit to send if the device is transmitting
+    fn transmit(&mut self) -> Option<bool>;
+
+    /// Called at the end of a time slot that the device wasn't transmitting in
+    fn receive(&mut self, bit: bool);
+}
+
+struct Line<S> {
+    clock: Rc<Cell<u64>>,
+    master_low_since: Option<u64>,
+    // time ranges where a device is pulling the bus low
+    device_low: Vec<(u64, u64)>,
+    // which slaves are transmitting in the current time slot
+    transmitting: Vec<bool>,
+    slaves: Vec<S>,
+}
+
+impl<S: Slave> Line<S> {
+    fn new(clock: Rc<Cell<u64>>, slaves: Vec<S>) -> Line<S> {
+        Line {
+            clock,
+            master_low_since: None,
+            device_low: Vec::new(),
+            transmitting: Vec::new(),
+            slaves,
+        }
+    }
+
+    fn set_low(&mut self) {
+        if self.master_low_since.is_some() {
+            return;
+        }
+        let now = self.clock.get();
+        self.master_low_since = Some(now);
+        self.transmitting.clear();
+        for slave in &mut self.slaves {
+            let hold = if slave.is_overdrive() { 4 } else { 30 };
+            let bit = slave.transmit();
+            if bit == Some(false) {
+                self.device_low.push((now, now + hold));
+            }
+            self.transmitting.push(bit.is_some());
+        }
+    }
+
+    fn set_high(&mut self) {
+        let since = match self.master_low_since.take() {
+            Some(since) => since,
+            None => return,
+        };
+        let now = self.clock.get();
+        let low_us = now - since;
+        for (slave, transmitting) in self.slaves.iter_mut().zip(&self.transmitting) {
+            let (reset_us, write_1_max_us) = if slave.is_overdrive() {
+                (48, 3)
+            } else {
+                (480, 15)
+            };
+            if low_us >= reset_us {
+                if slave.reset(low_us) {
+                    let (start, length) = if slave.is_overdrive() {
+                        (3, 10)
+                    } else {
+                        (15, 120)
+                    };
+                    self.device_low.push((now + start, now + start + length));
+                }
+            } else if !transmitting {
+                slave.receive(low_us < write_1_max_us);
+            }
+        }
+    }
+
+    fn is_high(&mut self) -> bool {
+        let now = self.clock.get();
+        self.device_low.retain(|(_, end)| *end > now);
+        self.master_low_since.is_none()
+            && !self
+                .device_low
+                .iter()
+                .any(|(start, end)| *start <= now && now < *end)
+    }
+}
+
+/// A pin on a bus with nothing attached, that returns an error for every operation once `fail`
+/// is set
+pub struct BrokenPin {
+    pub fail: Rc<Cell<bool>>,
+}
+
+impl BrokenPin {
+    fn result<T>(&self, value: T) -> Result<T, ()> {
+        if self.fail.get() {
+            Err(())
+        } else {
+            Ok(value)
+        }
+    }
+}
+
+impl InputPin for BrokenPin {
+    type Error = ();
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        self.result(true)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.result(false)
+    }
+}
+
+impl OutputPin for BrokenPin {
+    type Error = ();
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.result(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.result(())
+    }
+}
+
+/// The master's pin on a simulated bus
+pub struct MockPin<S> {
+    line: Rc<RefCell<Line<S>>>,
+}
+
+impl<S: Slave> InputPin for MockPin<S> {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.line.borrow_mut().is_high())
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(!self.line.borrow_mut().is_high())
+    }
+}
+
+impl<S: Slave> OutputPin for MockPin<S> {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.line.borrow_mut().set_low();
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.line.borrow_mut().set_high();
+        Ok(())
+    }
+}
+
+/// A change the master made to its pin, at a time on the simulated clock
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Edge {
+    /// The master pulled the bus low
+    Low(u64),
+
+    /// The master released the bus
+    High(u64),
+}
+
+/// The edges recorded by a `RecordingPin`, which can be checked while the pin is owned by a
+/// `OneWire`
+#[derive(Clone)]
+pub struct Transcript {
+    edges: Rc<RefCell<Vec<Edge>>>,
+}
+
+impl Transcript {
+    /// The edges recorded so far
+    pub fn edges(&self) -> Vec<Edge> {
+        self.edges.borrow().clone()
+    }
+
+    /// The edges recorded so far, with times relative to the first edge
+    pub fn relative_edges(&self) -> Vec<Edge> {
+        let edges = self.edges.borrow();
+        let start = match edges.first() {
+            Some(Edge::Low(time)) | Some(Edge::High(time)) => *time,
+            None => return Vec::new(),
+        };
+        edges
+            .iter()
+            .map(|edge| match edge {
+                Edge::Low(time) => Edge::Low(time - start),
+                Edge::High(time) => Edge::High(time - start),
+            })
+            .collect()
+    }
+
+    /// Forgets the edges recorded so far
+    pub fn clear(&self) {
+        self.edges.borrow_mut().clear();
+    }
+}
+
+/// Wraps a pin, and records every `set_low` and `set_high` with the time on the simulated clock,
+/// to check the exact sequence of edges a command sends against a known good transcript
+pub struct RecordingPin<P> {
+    pin: P,
+    clock: Rc<Cell<u64>>,
+    transcript: Transcript,
+}
+
+impl<P> RecordingPin<P> {
+    fn record(&self, edge: Edge) {
+        self.transcript.edges.borrow_mut().push(edge);
+    }
+}
+
+impl<P: InputPin> InputPin for RecordingPin<P> {
+    type Error = P::Error;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        self.pin.is_high()
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.pin.is_low()
+    }
+}
+
+impl<P: OutputPin> OutputPin for RecordingPin<P> {
+    type Error = P::Error;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.record(Edge::Low(self.clock.get()));
+        self.pin.set_low()
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.record(Edge::High(self.clock.get()));
+        self.pin.set_high()
+    }
+}
+
+/// A delay that advances the clock of a simulated bus instead of waiting
+pub struct MockDelay {
+    clock: Rc<Cell<u64>>,
+}
+
+impl DelayUs<u16> for MockDelay {
+    fn delay_us(&mut self, us: u16) {
+        self.clock.set(self.clock.get() + us as u64);
+    }
+}
+
+enum DeviceState {
+    // waiting for a reset pulse
+    Idle,
+    RomCommand {
+        bits: u8,
+        value: u8,
+    },
+    // step 0 sends the address bit, 1 sends the complement, and 2 receives the chosen bit
+    Search {
+        bit: u8,
+        step: u8,
+    },
+    // `overdrive_match` devices return to standard speed if the address doesn't match
+    MatchRom {
+        bits: u8,
+        value: u64,
+        overdrive_match: bool,
+    },
+    ReadRom {
+        bit: u8,
+    },
+    // selected, and receiving function command bytes
+    Function {
+        bits: u8,
+        value: u8,
+    },
+    // sending its power supply in every read slot, after READ_POWER_SUPPLY
+    PowerSupply,
+}
+
+/// A simulated device that responds to ROM commands
+pub struct MockDevice {
+    address: u64,
+    alarming: bool,
+    overdrive_capable: bool,
+    overdrive: bool,
+    // set when the device was the last one selected, so it responds to RESUME
+    resume: bool,
+    // the number of searches the device takes part in before it starts missing searches
+    searches_before_missing: u32,
+    // the number of searches the device doesn't take part in, to simulate a glitch
+    missed_searches: u32,
+    parasite_powered: bool,
+    received: Vec<u8>,
+    state: DeviceState,
+}
+
+impl MockDevice {
+    pub fn new(address: u64) -> MockDevice {
+        MockDevice {
+            address,
+            alarming: false,
+            overdrive_capable: false,
+            overdrive: false,
+            resume: false,
+            searches_before_missing: 0,
+            missed_searches: 0,
+            parasite_powered: false,
+            received: Vec::new(),
+            state: DeviceState::Idle,
+        }
+    }
+
+    /// Makes the device respond to an alarm search
+    pub fn alarming(mut self) -> MockDevice {
+        self.alarming = true;
+        self
+    }
+
+    /// Makes the device respond to the overdrive ROM commands
+    pub fn overdrive_capable(mut self) -> MockDevice {
+        self.overdrive_capable = true;
+        self
+    }
+
+    /// Makes the device report that it is parasite powered, in response to READ_POWER_SUPPLY
+    pub fn parasite_powered(mut self) -> MockDevice {
+        self.parasite_powered = true;
+        self
+    }
+
+    /// Makes the device ignore the next `count` searches, as if the bus had glitched
+    pub fn missing_from_searches(mut self, count: u32) -> MockDevice {
+        self.missed_searches = count;
+        self
+    }
+
+    /// Same as `missing_from_searches`, but the device takes part in `after` searches first, as if
+    /// its connection dropped out part way through a series of searches
+    pub fn missing_from_searches_after(mut self, after: u32, count: u32) -> MockDevice {
+        self.searches_before_missing = after;
+        self.missed_searches = count;
+        self
+    }
+
+    fn address_bit(&self, bit: u8) -> bool {
+        self.address & (1 << bit) != 0
+    }
+
+    fn select(&mut self) -> DeviceState {
+        self.resume = true;
+        DeviceState::Function { bits: 0, value: 0 }
+    }
+
+    fn rom_command(&mut self, command: u8) -> DeviceState {
+        if matches!(command, commands::SEARCH_NORMAL | commands::SEARCH_ALARM) {
+            if self.searches_before_missing > 0 {
+                self.searches_before_missing -= 1;
+            } else if self.missed_searches > 0 {
+                self.missed_searches -= 1;
+                return DeviceState::Idle;
+            }
+        }
+        match command {
+            commands::SEARCH_NORMAL => DeviceState::Search { bit: 0, step: 0 },
+            commands::SEARCH_ALARM if self.alarming => DeviceState::Search { bit: 0, step: 0 },
+            commands::MATCH_ROM => DeviceState::MatchRom {
+                bits: 0,
+                value: 0,
+                overdrive_match: false,
+            },
+            commands::OVERDRIVE_MATCH_ROM if self.overdrive_capable => {
+                let overdrive_match = !self.overdrive;
+                self.overdrive = true;
+                DeviceState::MatchRom {
+                    bits: 0,
+                    value: 0,
+                    overdrive_match,
+                }
+            }
+            commands::SKIP_ROM => {
+                self.resume = false;
+                DeviceState::Function { bits: 0, value: 0 }
+            }
+            commands::OVERDRIVE_SKIP_ROM if self.overdrive_capable => {
+                self.resume = false;
+                self.overdrive = true;
+                DeviceState::Function { bits: 0, value: 0 }
+            }
+            commands::READ_ROM => DeviceState::ReadRom { bit: 0 },
+            commands::RESUME if self.resume => DeviceState::Function { bits: 0, value: 0 },
+            _ => {
+                self.resume = false;
+                DeviceState::Idle
+            }
+        }
+    }
+}
+
+impl Slave for MockDevice {
+    fn is_overdrive(&self) -> bool {
+        self.overdrive
+    }
+
+    fn reset(&mut self, low_us: u64) -> bool {
+        if low_us >= 480 {
+            // a standard speed reset returns all devices to standard speed
+            self.overdrive = false;
+        }
+        self.state = DeviceState::RomCommand { bits: 0, value: 0 };
+        true
+    }
+
+    fn transmit(&mut self) -> Option<bool> {
+        match self.state {
+            DeviceState::PowerSupply => Some(!self.parasite_powered),
+            DeviceState::Search { bit, step: 0 } => {
+                self.state = DeviceState::Search { bit, step: 1 };
+                Some(self.address_bit(bit))
+            }
+            DeviceState::Search { bit, step: 1 } => {
+                self.state = DeviceState::Search { bit, step: 2 };
+                Some(!self.address_bit(bit))
+            }
+            DeviceState::ReadRom { bit } => {
+                self.state = if bit == 63 {
+                    self.select()
+                } else {
+                    DeviceState::ReadRom { bit: bit + 1 }
+                };
+                Some(self.address_bit(bit))
+            }
+            _ => None,
+        }
+    }
+
+    fn receive(&mut self, value: bool) {
+        self.state = match self.state {
+            DeviceState::RomCommand {
+                bits,
+                value: command,
+            } => {
+                let command = command | ((value as u8) << bits);
+                if bits == 7 {
+                    self.rom_command(command)
+                } else {
+                    DeviceState::RomCommand {
+                        bits: bits + 1,
+                        value: command,
+                    }
+                }
+            }
+            DeviceState::Search { bit, step: 2 } => {
+                if value != self.address_bit(bit) {
+                    self.resume = false;
+                    DeviceState::Idle
+                } else if bit == 63 {
+                    self.select()
+                } else {
+                    DeviceState::Search {
+                        bit: bit + 1,
+                        step: 0,
+                    }
+                }
+            }
+            DeviceState::MatchRom {
+                bits,
+                value: address,
+                overdrive_match,
+            } => {
+                let address = address | ((value as u64) << bits);
+                if bits < 63 {
+                    DeviceState::MatchRom {
+                        bits: bits + 1,
+                        value: address,
+                        overdrive_match,
+                    }
+                } else if address == self.address {
+                    self.select()
+                } else {
+                    if overdrive_match {
+                        self.overdrive = false;
+                    }
+                    self.resume = false;
+                    DeviceState::Idle
+                }
+            }
+            DeviceState::Function { bits, value: byte } => {
+                let byte = byte | ((value as u8) << bits);
+                if bits == 7 {
+                    self.received.push(byte);
+                    if byte == commands::READ_POWER_SUPPLY {
+                        DeviceState::PowerSupply
+                    } else {
+                        DeviceState::Function { bits: 0, value: 0 }
+                    }
+                } else {
+                    DeviceState::Function {
+                        bits: bits + 1,
+                        value: byte,
+                    }
+                }
+            }
+            _ => DeviceState::Idle,
+        };
+    }
+}
+
+/// A simulated bus with a set of devices attached
+pub struct MockBus {
+    clock: Rc<Cell<u64>>,
+    line: Rc<RefCell<Line<MockDevice>>>,
+}
+
+impl MockBus {
+    pub fn new(devices: Vec<MockDevice>) -> MockBus {
+        let clock = Rc::new(Cell::new(0));
+        MockBus {
+            line: Rc::new(RefCell::new(Line::new(clock.clone(), devices))),
+            clock,
+        }
+    }
+
+    /// The master's pin, to construct a `OneWire` with
+    pub fn pin(&self) -> MockPin<MockDevice> {
+        MockPin {
+            line: self.line.clone(),
+        }
+    }
+
+    /// The master's pin, wrapped to record every edge, and the transcript it records to
+    pub fn recording_pin(&self) -> (RecordingPin<MockPin<MockDevice>>, Transcript) {
+        let transcript = Transcript {
+            edges: Rc::new(RefCell::new(Vec::new())),
+        };
+        let pin = RecordingPin {
+            pin: self.pin(),
+            clock: self.clock.clone(),
+            transcript: transcript.clone(),
+        };
+        (pin, transcript)
+    }
+
+    pub fn delay(&self) -> MockDelay {
+        MockDelay {
+            clock: self.clock.clone(),
+        }
+    }
+
+    /// Holds the bus low from `start_us` until `end_us`, as a misbehaving device would
+    pub fn hold_low(&self, start_us: u64, end_us: u64) {
+        self.line.borrow_mut().device_low.push((start_us, end_us));
+    }
+
+    /// The simulated time, in microseconds
+    pub fn now(&self) -> u64 {
+        self.clock.get()
+    }
+
+    /// The bytes the device at `index` has received after being selected
+    pub fn received(&self, index: usize) -> Vec<u8> {
+        self.line.borrow().slaves[index].received.clone()
+    }
+}
+
+/// An expected step of the communication on a `ReplayBus`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A reset pulse from the master, and whether a device responds with a presence pulse
+    Reset { present: bool },
+
+    /// A byte written by the master
+    Write(u8),
+
+    /// A byte sent to the master
+    Read(u8),
+}
+
+impl Event {
+    /// The events of a reset, MATCH_ROM with `address`, and then `command`, which start most
+    /// transactions with a single device
+    pub fn match_rom(address: &Address, command: u8) -> Vec<Event> {
+        let mut events = std::vec![
+            Event::Reset { present: true },
+            Event::Write(commands::MATCH_ROM),
+        ];
+        events.extend(address.to_le_bytes().iter().map(|byte| Event::Write(*byte)));
+        events.push(Event::Write(command));
+        events
+    }
+}
+
+/// Follows a recorded sequence of events, and panics if the master deviates from it
+pub struct Script {
+    events: Vec<Event>,
+    position: usize,
+    bits: u8,
+    value: u8,
+}
+
+impl Script {
+    fn advance(&mut self) {
+        self.position += 1;
+        self.bits = 0;
+        self.value = 0;
+    }
+
+    fn unexpected(&self, actual: &str) -> ! {
+        panic!(
+            "event {}: expected {:02X?}, but the master {}",
+            self.position,
+            self.events.get(self.position),
+            actual
+        );
+    }
+}
+
+impl Slave for Script {
+    fn reset(&mut self, _low_us: u64) -> bool {
+        match self.events.get(self.position) {
+            Some(Event::Reset { present }) if self.bits == 0 => {
+                let present = *present;
+                self.advance();
+                present
+            }
+            _ => self.unexpected("sent a reset pulse"),
+        }
+    }
+
+    fn transmit(&mut self) -> Option<bool> {
+        match self.events.get(self.position) {
+            Some(Event::Read(byte)) => {
+                let bit = byte & (1 << self.bits) != 0;
+                self.bits += 1;
+                if self.bits == 8 {
+                    self.advance();
+                }
+                Some(bit)
+            }
+            _ => None,
+        }
+    }
+
+    fn receive(&mut self, bit: bool) {
+        match self.events.get(self.position) {
+            Some(Event::Write(expected)) => {
+                let expected = *expected;
+                self.value |= (bit as u8) << self.bits;
+                self.bits += 1;
+                if self.bits == 8 {
+                    if self.value != expected {
+                        self.unexpected(&std::format!("wrote {:02X}", self.value));
+                    }
+                    self.advance();
+                }
+            }
+            _ => self.unexpected("wrote a bit"),
+        }
+    }
+}
+
+/// A simulated bus that replays a recorded sequence of events (such as a captured transaction with
+/// a real device), panicking with the position of the first difference if the master deviates
+/// from it
+pub struct ReplayBus {
+    clock: Rc<Cell<u64>>,
+    line: Rc<RefCell<Line<Script>>>,
+}
+
+impl ReplayBus {
+    pub fn new(events: Vec<Event>) -> ReplayBus {
+        let clock = Rc::new(Cell::new(0));
+        let script = Script {
+            events,
+            position: 0,
+            bits: 0,
+            value: 0,
+        };
+        ReplayBus {
+            line: Rc::new(RefCell::new(Line::new(clock.clone(), std::vec![script]))),
+            clock,
+        }
+    }
+
+    /// The master's pin, to construct a `OneWire` with
+    pub fn pin(&self) -> MockPin<Script> {
+        MockPin {
+            line: self.line.clone(),
+        }
+    }
+
+    pub fn delay(&self) -> MockDelay {
+        MockDelay {
+            clock: self.clock.clone(),
+        }
+    }
+
+    /// The simulated time, in microseconds
+    pub fn now(&self) -> u64 {
+        self.clock.get()
+    }
+
+    /// Panics if any events of the script haven't happened yet
+    pub fn finish(&self) {
+        let line = self.line.borrow();
+        let script = &line.slaves[0];
+        if script.position < script.events.len() {
+            script.unexpected("finished");
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{Address, OneWire, OneWireResult};
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// A device that has been selected, and is addressed again with a RESUME command instead of
+/// sending its full address for each command
+pub struct Selected<'a, T> {
+    onewire: &'a mut OneWire<T>,
+    address: Address,
+}
+
+impl<'a, T, E> Selected<'a, T>
+where
+    T: InputPin<Error = E>,
+    T: OutputPin<Error = E>,
+{
+    pub(crate) fn new(onewire: &'a mut OneWire<T>, address: Address) -> Selected<'a, T> {
+        Selected { onewire, address }
+    }
+
+    /// The address of the selected device
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// The underlying bus
+    pub fn bus(&mut self) -> &mut OneWire<T> {
+        self.onewire
+    }
+
+    /// Sends a reset, followed by RESUME and then the supplied command
+    /// If another device was addressed through [`Selected::bus`] in the meantime, the
+    /// device is re-addressed with MATCH_ROM at the current bus speed instead
+    /// This should be followed by any reading/writing, if needed by the command used
+    pub fn send_command(
+        &mut self,
+        command: u8,
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E> {
+        if self.onewire.selected == Some(self.address) {
+            self.onewire.send_command_resume(command, delay)
+        } else {
+            self.onewire
+                .send_command(command, Some(&self.address), delay)
+        }
+    }
+
+    pub fn read_bytes(
+        &mut self,
+        output: &mut [u8],
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E> {
+        self.onewire.read_bytes(output, delay)
+    }
+
+    pub fn write_bytes(
+        &mut self,
+        bytes: &[u8],
+        delay: &mut impl DelayUs<u16>,
+    ) -> OneWireResult<(), E> {
+        self.onewire.write_bytes(bytes, delay)
+    }
+}
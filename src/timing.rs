@@ -0,0 +1,110 @@
+/// The speed the bus communicates at
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Speed {
+    /// The default speed that all devices use after a standard speed reset
+    Standard,
+
+    /// Roughly 10x faster than standard speed. Only supported by some devices, which must first be
+    /// switched to overdrive with an overdrive ROM command
+    Overdrive,
+}
+
+/// Durations (in microseconds) used when driving the bus.
+///
+/// The defaults follow the Maxim recommended timing for each speed.
+/// <https://www.maximintegrated.com/en/design/technical-documents/app-notes/1/126.html>
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Timing {
+    /// How long the bus is held low for a reset pulse
+    pub reset_low_us: u16,
+
+    /// Start of the presence detect window, measured from the end of the reset pulse.
+    /// Devices wait 15-60µs (2-6µs at overdrive) before pulling the bus low.
+    pub presence_window_start_us: u16,
+
+    /// End of the presence detect window, measured from the end of the reset pulse.
+    /// A device is reported as present if the bus is low at any point in the window.
+    pub presence_window_end_us: u16,
+
+    /// Total time the bus is released after the reset pulse (including the presence detect window)
+    /// before the next command is sent
+    pub reset_recovery_us: u16,
+
+    /// How long the bus is held low to write a 1 bit
+    pub write_1_low_us: u16,
+
+    /// How long the bus is released after writing a 1 bit
+    pub write_1_recovery_us: u16,
+
+    /// How long the bus is held low to write a 0 bit
+    pub write_0_low_us: u16,
+
+    /// How long the bus is released after writing a 0 bit
+    pub write_0_recovery_us: u16,
+
+    /// How long the bus is held low to start a read slot
+    pub read_low_us: u16,
+
+    /// How long after releasing the bus the bit is sampled in a read slot
+    pub read_sample_us: u16,
+
+    /// How long to wait after sampling before the next slot
+    pub read_recovery_us: u16,
+
+    /// How long the bus is left released (idle) at the end of a complete transaction, such as a
+    /// device search, before another transaction can start. Some devices need extra time to settle
+    /// between back-to-back transactions
+    pub inter_transaction_us: u16,
+
+    /// How long the bus is left released between the triplets (bit, complement, chosen bit) of a
+    /// device search. Very long, high capacitance buses may need time to settle between triplets
+    pub inter_triplet_us: u16,
+
+    /// The longest time the bus can take to rise after being released for this speed to be reliable.
+    /// This depends on the pull-up resistor and the capacitance of the bus
+    pub max_rise_time_us: u16,
+}
+
+impl Timing {
+    /// Maxim recommended standard speed timing
+    pub const STANDARD: Timing = Timing {
+        reset_low_us: 480,
+        presence_window_start_us: 60,
+        presence_window_end_us: 240,
+        reset_recovery_us: 480,
+        write_1_low_us: 6,
+        write_1_recovery_us: 64,
+        write_0_low_us: 60,
+        write_0_recovery_us: 10,
+        read_low_us: 6,
+        read_sample_us: 9,
+        read_recovery_us: 55,
+        inter_transaction_us: 0,
+        inter_triplet_us: 0,
+        max_rise_time_us: 5,
+    };
+
+    /// Maxim recommended overdrive speed timing, rounded to whole microseconds
+    pub const OVERDRIVE: Timing = Timing {
+        reset_low_us: 70,
+        presence_window_start_us: 2,
+        presence_window_end_us: 10,
+        reset_recovery_us: 48,
+        write_1_low_us: 1,
+        write_1_recovery_us: 8,
+        write_0_low_us: 8,
+        write_0_recovery_us: 3,
+        read_low_us: 1,
+        read_sample_us: 1,
+        read_recovery_us: 7,
+        inter_transaction_us: 0,
+        inter_triplet_us: 0,
+        max_rise_time_us: 1,
+    };
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Timing::STANDARD
+    }
+}